@@ -0,0 +1,95 @@
+/*! The inverse cubic method against plain bisection and false position on a few kinds of function, and the cost of
+ *  calling func through a pointer rather than inlining it. Wall time depends on how expensive func is, so the
+ *  iterations and evaluations of each method are printed first, run with
+ *    cargo bench --bench methods
+ */
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use inverse_cubic_interpolation::{bisection_solve, false_position_solve, inv_cubic_solve, inv_cubic_solve_dyn, Solver};
+
+const TOL: f64 = 1e-12;
+const MAX_ITERS: usize = 1000;
+
+/** A test function with its derivative and a bracket around a root */
+struct Problem {
+    name: &'static str,
+    func: fn(f64) -> f64,
+    deriv: fn(f64) -> f64,
+    bracket: (f64, f64)
+}
+
+fn problems() -> Vec<Problem> {
+    vec![
+        Problem {
+            name: "smooth",
+            func: |x| x.exp() - 2.0,
+            deriv: |x| x.exp(),
+            bracket: (0.0, 3.0)
+        },
+        Problem {
+            name: "steep",
+            func: |x| (1000.0*(x - 0.3)).atan(),
+            deriv: |x| 1000.0/(1.0 + (1000.0*(x - 0.3)).powi(2)),
+            bracket: (0.0, 1.0)
+        },
+        Problem {
+            name: "oscillatory",
+            func: |x| x - 0.3 + 0.25*(30.0*(x - 0.3)).sin(),
+            deriv: |x| 1.0 + 7.5*(30.0*(x - 0.3)).cos(),
+            bracket: (0.0, 1.0)
+        }
+    ]
+}
+
+/** The solver settings that match each of the entry points being timed */
+fn methods() -> [(&'static str, Solver); 3] {
+    let solver = Solver::new().tol(TOL).max_iters(MAX_ITERS);
+    [("inverse cubic", solver),
+     ("false position", solver.cubic(false)),
+     ("bisection", solver.cubic(false).bisection_freq(1))]
+}
+
+fn print_work() {
+    println!("{:<12} {:<15} {:>10} {:>11} {:>12}", "function", "method", "iterations", "func_evals", "deriv_evals");
+    for p in problems() {
+        for (method, solver) in methods().iter() {
+            match solver.solve_with_stats(p.bracket.0, p.bracket.1, p.func, p.deriv) {
+                Ok((_, stats)) => println!("{:<12} {:<15} {:>10} {:>11} {:>12}",
+                                           p.name, method, stats.iterations, stats.func_evals, stats.deriv_evals),
+                Err(e) => println!("{:<12} {:<15} failed: {}", p.name, method, e)
+            }
+        }
+    }
+}
+
+fn bench_methods(c: &mut Criterion) {
+    print_work();
+
+    for p in problems() {
+        let mut group = c.benchmark_group(p.name);
+        let (x0, x1) = p.bracket;
+        group.bench_function("inverse cubic", |b| b.iter(|| inv_cubic_solve(black_box(x0), black_box(x1), TOL, MAX_ITERS, p.func, p.deriv)));
+        group.bench_function("false position", |b| b.iter(|| false_position_solve(black_box(x0), black_box(x1), TOL, MAX_ITERS, p.func)));
+        group.bench_function("bisection", |b| b.iter(|| bisection_solve(black_box(x0), black_box(x1), TOL, MAX_ITERS, p.func)));
+        group.finish();
+    }
+}
+
+/** The same solve of a cheap polynomial with func as a closure, which can be inlined into the solver loop, and behind
+  * a function pointer or a trait object, which can't. The difference is the call overhead, as func costs next to nothing.
+  */
+fn bench_inlining(c: &mut Criterion) {
+    let func = |x: f64| x*x*x - 2.0*x - 5.0;
+    let deriv = |x: f64| 3.0*x*x - 2.0;
+    let func_ptr: fn(f64) -> f64 = func;
+    let deriv_ptr: fn(f64) -> f64 = deriv;
+
+    let mut group = c.benchmark_group("cheap polynomial");
+    group.bench_function("closure", |b| b.iter(|| inv_cubic_solve(black_box(2.0), black_box(3.0), TOL, MAX_ITERS, func, deriv)));
+    group.bench_function("fn pointer", |b| b.iter(|| inv_cubic_solve(black_box(2.0), black_box(3.0), TOL, MAX_ITERS, black_box(func_ptr), black_box(deriv_ptr))));
+    group.bench_function("dyn Fn", |b| b.iter(|| inv_cubic_solve_dyn(black_box(2.0), black_box(3.0), TOL, MAX_ITERS, black_box(&func), black_box(&deriv))));
+    group.finish();
+}
+
+criterion_group!(benches, bench_methods, bench_inlining);
+criterion_main!(benches);
@@ -0,0 +1,77 @@
+/*! Solving for functions that are evaluated asynchronously, e.g. by querying a remote service or reading from disk */
+
+use core::future::Future;
+use num_traits::Float;
+
+use crate::{BracketState, Solver, SolveError};
+use crate::convergence::Converged;
+use crate::predictor::CubicPredictor;
+
+/** The same as inv_cubic_solve, except func and deriv return futures, which are awaited for each evaluation, so that a
+  * runtime thread isn't blocked. The steps taken are exactly those of inv_cubic_solve, the solver picks each point from
+  * the current bracket alone and only then awaits func there, followed by deriv if the point becomes an end of the
+  * bracket. The evaluations are awaited one after the other, never concurrently.
+  */
+pub async fn inv_cubic_solve_async<T, F, Fut, D, DFut>(x0: T, x1: T, tol: T, max_iters: usize, func: F, deriv: D) -> Result<T, SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = T>,
+    D: Fn(T) -> DFut,
+    DFut: Future<Output = T>
+{
+    let cfg = Solver::new().tol(tol).max_iters(max_iters);
+
+    // the same set up as BracketState::new
+    let (x0, x1) = if x1 < x0 { (x1, x0) } else { (x0, x1) };
+    let f0 = func(x0).await;
+    let f1 = if x1 == x0 { f0 } else { func(x1).await };
+    let exact_root = BracketState::check_ends(x0, x1, f0, f1)?;
+    let (df0, df1) = if exact_root { (T::nan(), T::nan()) } else { (deriv(x0).await, deriv(x1).await) };
+    let mut state = BracketState::from_ends(x0, x1, f0, f1, df0, df1);
+
+    loop {
+        if state.f_min() == T::zero() || cfg.is_converged(state.x0, state.x1, state.f0, state.f1) {
+            return Ok(state.root());
+        }
+        if state.at_machine_precision() {
+            return Err(SolveError::MachinePrecisionReached { best: state.root() });
+        }
+        if state.n_iters > max_iters {
+            return Err(SolveError::DidNotConverge { best: state.root() });
+        }
+
+        let proposal = state.propose(&cfg, None::<&fn(T) -> T>, &CubicPredictor);
+        let f_new = func(proposal.x).await;
+        if !f_new.is_finite() {
+            return Err(SolveError::NonFiniteFunctionValue { x: proposal.x });
+        }
+        let df_new = if state.moves_end(proposal.x) { deriv(proposal.x).await } else { T::nan() };
+        state = state.accept(&cfg, proposal, f_new, df_new)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::task::{Context, Poll, Waker};
+
+    /** Run a future that never has to wait, as the futures here are all ready as soon as they are polled */
+    fn block_on<Fut: Future>(future: Fut) -> Fut::Output {
+        let mut future = core::pin::pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn async_solve_takes_the_same_steps_as_the_sync_one() {
+        let func = |x: f64| x.sin() + x.powi(3) - 0.5;
+        let deriv = |x: f64| x.cos() + 3.0*x*x;
+        let root = block_on(inv_cubic_solve_async(-1.0, 2.0, 1e-12, 100, |x| async move { func(x) }, |x| async move { deriv(x) }));
+        assert_eq!(root, crate::inv_cubic_solve(-1.0, 2.0, 1e-12, 100, func, deriv));
+    }
+}
@@ -0,0 +1,96 @@
+/*! Solving many independent problems, either sharing the same function or each with its own */
+
+use num_traits::Float;
+
+use crate::{inv_cubic_solve, SolveError};
+
+/** Run inv_cubic_solve on each bracket in inputs, returning the results in the same order */
+pub fn inv_cubic_solve_batch<T, F, D>(inputs: &[(T, T)], tol: T, max_iters: usize, func: F, deriv: D) -> Vec<Result<T, SolveError<T>>>
+where
+    T: Float,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T
+{
+    inputs.iter()
+          .map(|&(x0, x1)| inv_cubic_solve(x0, x1, tol, max_iters, &func, &deriv))
+          .collect()
+}
+
+/** The same as inv_cubic_solve_batch, except the brackets are solved in parallel with rayon. The results are still
+  * in the same order as the inputs.
+  */
+#[cfg(feature = "parallel")]
+pub fn inv_cubic_solve_batch_par<T, F, D>(inputs: &[(T, T)], tol: T, max_iters: usize, func: F, deriv: D) -> Vec<Result<T, SolveError<T>>>
+where
+    T: Float + Send + Sync,
+    F: Fn(T) -> T + Sync,
+    D: Fn(T) -> T + Sync
+{
+    use rayon::prelude::*;
+
+    inputs.par_iter()
+          .map(|&(x0, x1)| inv_cubic_solve(x0, x1, tol, max_iters, &func, &deriv))
+          .collect()
+}
+
+/** Solve a decoupled system of equations, where equation i is `funcs[i](x) = 0` with derivative `derivs[i]` on the
+  * bracket `brackets[i]`. Each equation is solved on its own with inv_cubic_solve, with at most max_iters iterations,
+  * and the results are in the same order as the equations. If the three slices don't all have the same length, the
+  * error is MismatchedLengths and nothing is solved.
+  */
+pub fn inv_cubic_solve_system<T, F, D>(brackets: &[(T, T)], funcs: &[F], derivs: &[D], tol: T, max_iters: usize) -> Result<Vec<Result<T, SolveError<T>>>, SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T
+{
+    if funcs.len() != brackets.len() || derivs.len() != brackets.len() {
+        return Err(SolveError::MismatchedLengths);
+    }
+
+    Ok(brackets.iter()
+               .zip(funcs.iter().zip(derivs))
+               .map(|(&(x0, x1), (func, deriv))| inv_cubic_solve(x0, x1, tol, max_iters, func, deriv))
+               .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn serial_and_parallel_batches_agree() {
+        let func = |x: f64| x.sin() + x.powi(3);
+        let deriv = |x: f64| x.cos() + 3.0*x*x;
+        // with a bracket without a root among the others, whose error must stay in place
+        let mut inputs: Vec<(f64, f64)> = (0..1000).map(|i| (-1.0 - i as f64/1000.0, 0.5 + i as f64/7.0)).collect();
+        inputs.insert(500, (1.0, 2.0));
+
+        let serial = inv_cubic_solve_batch(&inputs, 1e-12, 100, func, deriv);
+        let parallel = inv_cubic_solve_batch_par(&inputs, 1e-12, 100, func, deriv);
+        assert_eq!(serial, parallel);
+        assert_eq!(serial[500], Err(SolveError::NonBracketingInterval));
+    }
+
+    #[test]
+    fn system_solves_each_equation_on_its_own() {
+        let funcs: [fn(f64) -> f64; 3] = [|x| x*x - 2.0, |x| x.cos() - x, |x| x.exp() - 3.0];
+        let derivs: [fn(f64) -> f64; 3] = [|x| 2.0*x, |x| -x.sin() - 1.0, |x| x.exp()];
+        let roots = inv_cubic_solve_system(&[(0.0, 2.0), (0.0, 1.0), (0.0, 2.0)], &funcs, &derivs, 1e-13, 100).unwrap();
+        assert_eq!(roots.len(), 3);
+        assert!((roots[0].unwrap() - 2f64.sqrt()).abs() < 1e-13);
+        assert!((roots[1].unwrap() - 0.7390851332151607).abs() < 1e-13);
+        assert!((roots[2].unwrap() - 3f64.ln()).abs() < 1e-13);
+    }
+
+    #[test]
+    fn system_with_mismatched_lengths_is_an_error() {
+        let funcs: [fn(f64) -> f64; 2] = [|x| x, |x| x - 1.0];
+        let derivs: [fn(f64) -> f64; 2] = [|_| 1.0, |_| 1.0];
+        let result = inv_cubic_solve_system(&[(-1.0, 1.0)], &funcs, &derivs, 1e-13, 100);
+        assert_eq!(result, Err(SolveError::MismatchedLengths));
+        let result = inv_cubic_solve_system(&[(-1.0, 1.0), (0.0, 2.0)], &funcs, &derivs[..1], 1e-13, 100);
+        assert_eq!(result, Err(SolveError::MismatchedLengths));
+    }
+}
@@ -0,0 +1,130 @@
+/*! The solver for arbitrary precision decimal floats from dashu, e.g. for roots needed to hundreds of digits. These
+  * own heap memory, so they aren't Copy and can't implement Float, and the arithmetic here is all by reference instead.
+  */
+
+use dashu_float::DBig;
+use dashu_float::ops::Abs;
+
+use crate::{SolveError, BISECTION_FREQ};
+
+/** An end of the bracket, with the function and its derivative there */
+struct End {
+    x: DBig,
+    f: DBig,
+    df: DBig
+}
+
+impl End {
+    fn residual(&self) -> DBig {
+        self.f.clone().abs()
+    }
+}
+
+/** The root of the inverse cubic through the ends a and b, as from two_point_cubic_inverse, or None if f is the same
+  * at both ends, a derivative is zero, or the root isn't strictly inside the bracket
+  */
+fn inverse_cubic(a: &End, b: &End) -> Option<DBig> {
+    if a.f == b.f || a.df == DBig::ZERO || b.df == DBig::ZERO {
+        return None;
+    }
+    let two = DBig::from(2u8);
+    let three = DBig::from(3u8);
+
+    // the Hermite form of two_point_cubic_inverse, with the slopes of the inverse scaled by h = f1 - f0
+    let h = &b.f - &a.f;
+    let t = -&a.f/&h;
+    let t2 = &t*&t;
+    let t3 = &t2*&t;
+    let h00 = &two*&t3 - &three*&t2 + DBig::ONE;
+    let h10 = &t3 - &two*&t2 + &t;
+    let h01 = &three*&t2 - &two*&t3;
+    let h11 = &t3 - &t2;
+    let x = &a.x*&h00 + (&h/&a.df)*&h10 + &b.x*&h01 + (&h/&b.df)*&h11;
+
+    let (lo, hi) = if a.x < b.x { (&a.x, &b.x) } else { (&b.x, &a.x) };
+    if &x > lo && &x < hi { Some(x) } else { None }
+}
+
+/** The same as inv_cubic_solve, for DBig. The working precision is that of x0 and x1, e.g.
+  * `DBig::from_str("1.5")?.with_precision(110).value()` for 110 digits, and func and deriv should return values of
+  * the same precision. The steps are the inverse cubic, or a bisection every BISECTION_FREQ iterations, whenever
+  * the last step didn't halve the bracket, and whenever the cubic's root isn't strictly inside the bracket. The
+  * method has converged once the width of the bracket or the residual at its better end is below tol, and gives up
+  * with DidNotConverge after max_iters iterations.
+  *
+  * None of the settings of Solver apply here, and since DBig has no NaN, neither does NonFiniteFunctionValue.
+  */
+pub fn inv_cubic_solve_big<F, D>(x0: &DBig, x1: &DBig, tol: &DBig, max_iters: usize, func: F, deriv: D) -> Result<DBig, SolveError<DBig>>
+where
+    F: Fn(&DBig) -> DBig,
+    D: Fn(&DBig) -> DBig
+{
+    let two = DBig::from(2u8);
+    let end = |x: &DBig| End { x: x.clone(), f: func(x), df: deriv(x) };
+
+    let (mut a, mut b) = (end(x0), end(x1));
+    if a.f == DBig::ZERO {
+        return Ok(a.x);
+    }
+    if b.f == DBig::ZERO {
+        return Ok(b.x);
+    }
+    if a.f.sign() == b.f.sign() {
+        return Err(SolveError::NonBracketingInterval);
+    }
+
+    let mut width = (&b.x - &a.x).abs();
+    let mut bisect = false;
+    let mut n_iters = 0;
+    loop {
+        let best = if a.residual() < b.residual() { &a } else { &b };
+        if width < *tol || best.residual() < *tol {
+            return Ok(best.x.clone());
+        }
+        if n_iters == max_iters {
+            return Err(SolveError::DidNotConverge { best: best.x.clone() });
+        }
+
+        let x = match inverse_cubic(&a, &b) {
+            Some(x) if !bisect && (n_iters + 1) % BISECTION_FREQ != 0 => x,
+            _ => (&a.x + &b.x)/&two
+        };
+        let next = end(&x);
+        if next.f == DBig::ZERO {
+            return Ok(x);
+        }
+        if next.f.sign() == a.f.sign() { a = next } else { b = next }
+
+        let next_width = (&b.x - &a.x).abs();
+        bisect = &next_width*&two > width;
+        width = next_width;
+        n_iters += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::str::FromStr;
+
+    // sqrt(2) to 110 digits
+    const SQRT_2: &str = "1.4142135623730950488016887242096980785696718753769480731766797379907324784621070388503875343276415727350138462";
+
+    fn big(x: &str) -> DBig {
+        DBig::from_str(x).unwrap().with_precision(120).value()
+    }
+
+    #[test]
+    fn sqrt_2_to_100_digits() {
+        let two = big("2");
+        let root = inv_cubic_solve_big(&big("1"), &big("2"), &big("1e-105"), 100, |x| x*x - &two, |x| &two*x).unwrap();
+        assert!((&root - big(SQRT_2)).abs() < big("1e-100"), "{}", root);
+    }
+
+    #[test]
+    fn same_signs_are_not_a_bracket() {
+        let two = big("2");
+        let result = inv_cubic_solve_big(&big("2"), &big("3"), &big("1e-50"), 100, |x| x*x - &two, |x| &two*x);
+        assert_eq!(result, Err(SolveError::NonBracketingInterval));
+    }
+}
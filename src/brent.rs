@@ -0,0 +1,149 @@
+/*! Brent's method, a derivative-free alternative to the inverse cubic solver */
+
+use num_traits::Float;
+
+use crate::{constant, SolveError};
+use crate::solver::opposite_signs;
+
+/** Find a root of func in [x0, x1] by Brent's method, which combines bisection, the secant method, and inverse quadratic
+  * interpolation. As with inv_cubic_solve, f(x0) and f(x1) must have opposite signs, and the method has converged once
+  * the bracket width or the residual is below tol.
+  */
+pub fn brent_solve<T, F>(x0: T, x1: T, tol: T, max_iters: usize, func: F) -> Result<T, SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> T
+{
+    let two: T = constant(2.0);
+    let three: T = constant(3.0);
+    let half: T = constant(0.5);
+
+    let mut a = x0;
+    let mut b = x1;
+    let mut fa = func(a);
+    let mut fb = func(b);
+
+    if !fa.is_finite() {
+        return Err(SolveError::NonFiniteFunctionValue { x: a });
+    }
+    if !fb.is_finite() {
+        return Err(SolveError::NonFiniteFunctionValue { x: b });
+    }
+    if fa == T::zero() {
+        return Ok(a);
+    }
+    if fb == T::zero() {
+        return Ok(b);
+    }
+    if !opposite_signs(fa, fb) {
+        return Err(SolveError::NonBracketingInterval);
+    }
+
+    // b is the best point so far, and [b, c] always brackets the root, a is the previous value of b
+    let mut c = b;
+    let mut fc = fb;
+    let mut d = b - a;
+    let mut e = d;
+
+    for _ in 0..max_iters {
+        // the signs are compared directly, a product of two subnormal values would underflow to zero
+        if !opposite_signs(fb, fc) {
+            c = a;
+            fc = fa;
+            d = b - a;
+            e = d;
+        }
+        if fc.abs() < fb.abs() {
+            a = b;
+            b = c;
+            c = a;
+            fa = fb;
+            fb = fc;
+            fc = fa;
+        }
+
+        // if the method has converged, return the best point
+        if fb == T::zero() || (c - b).abs() < tol || fb.abs() < tol {
+            return Ok(b);
+        }
+
+        // smallest step allowed, so that the bracket keeps shrinking
+        let tol1 = two*T::epsilon()*b.abs() + half*tol;
+        let xm = half*(c - b);
+
+        if e.abs() >= tol1 && fa.abs() > fb.abs() {
+            // try inverse quadratic interpolation, or the secant method if only two distinct points are available
+            let s = fb/fa;
+            let (mut p, mut q);
+            if a == c {
+                p = two*xm*s;
+                q = T::one() - s;
+            } else {
+                let q0 = fa/fc;
+                let r = fb/fc;
+                p = s*(two*xm*q0*(q0 - r) - (b - a)*(r - T::one()));
+                q = (q0 - T::one())*(r - T::one())*(s - T::one());
+            }
+            if p > T::zero() {
+                q = -q;
+            }
+            p = p.abs();
+
+            // accept the interpolation only if it stays in the bracket and converges quickly enough
+            let min1 = three*xm*q - (tol1*q).abs();
+            let min2 = (e*q).abs();
+            if two*p < min1.min(min2) {
+                e = d;
+                d = p/q;
+            } else {
+                d = xm;
+                e = d;
+            }
+        } else {
+            d = xm;
+            e = d;
+        }
+
+        a = b;
+        fa = fb;
+        b = if d.abs() > tol1 { b + d } else { b + tol1.abs()*xm.signum() };
+        fb = func(b);
+        if !fb.is_finite() {
+            return Err(SolveError::NonFiniteFunctionValue { x: b });
+        }
+    }
+
+    Err(SolveError::DidNotConverge { best: b })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inv_cubic_solve;
+
+    #[test]
+    fn brent_and_inverse_cubic_agree() {
+        let func = |x: f64| x.sin() + x.powi(3);
+        let tol = 1e-12;
+        for &(x0, x1) in &[(-1.0, 0.5), (-0.3, 2.0)] {
+            let brent = brent_solve(x0, x1, tol, 100, func).unwrap();
+            let cubic = inv_cubic_solve(x0, x1, tol, 100, func, |x| x.cos() + 3.0*x*x).unwrap();
+            assert!((brent - cubic).abs() < tol, "{} vs {}", brent, cubic);
+        }
+    }
+
+    #[test]
+    fn brent_checks_the_bracket() {
+        assert_eq!(brent_solve(1.0, 2.0, 1e-12, 100, |x: f64| x), Err(SolveError::NonBracketingInterval));
+        assert_eq!(brent_solve(0.0, 2.0, 1e-12, 100, |x: f64| x), Ok(0.0));
+    }
+
+    #[test]
+    fn subnormal_residuals_keep_their_signs() {
+        // every value of func on the bracket is subnormal, and the product of any two underflows to zero
+        let func = |x: f64| (x - 0.3)*1e-308;
+        assert_eq!(func(0.0)*func(1.0), 0.0);
+        let root = brent_solve(0.0, 1.0, 0.0, 200, func).unwrap();
+        assert!((root - 0.3).abs() < 1e-14, "{}", root);
+    }
+}
@@ -0,0 +1,89 @@
+/*! Complex roots by Muller's method, where there is no bracket to keep, so none of the safeguards of the real solver apply */
+
+use num_complex::Complex;
+use num_traits::Float;
+
+use crate::{constant, SolveError};
+
+/** Find a complex root of func by Muller's method, starting from z0, z1 and their midpoint. The quadratic through the
+  * last three points has complex roots in general, so unlike muller_solve the iteration can leave the real line, e.g.
+  * to find the roots of z^2 + 1 from real starting points. The method has converged once the step or the residual is
+  * below tol in absolute value, and gives up with DidNotConverge after max_iters iterations.
+  *
+  * Without a bracket there is no guarantee of convergence, and which root is found depends on the starting points.
+  */
+pub fn complex_solve<T, F>(z0: Complex<T>, z1: Complex<T>, tol: T, max_iters: usize, func: F) -> Result<Complex<T>, SolveError<Complex<T>>>
+where
+    T: Float,
+    F: Fn(Complex<T>) -> Complex<T>
+{
+    let two: T = constant(2.0);
+    let four: T = constant(4.0);
+
+    let eval = |z: Complex<T>| {
+        let f = func(z);
+        if f.re.is_finite() && f.im.is_finite() { Ok(f) } else { Err(SolveError::NonFiniteFunctionValue { x: z }) }
+    };
+
+    // the three starting points are z0, the midpoint and z1, so that the first step is from the last given point
+    let (mut z0, mut z1, mut z2) = (z0, (z0 + z1)/two, z1);
+    let (mut f0, mut f1, mut f2) = (eval(z0)?, eval(z1)?, eval(z2)?);
+
+    for _ in 0..max_iters {
+        if f2 == Complex::new(T::zero(), T::zero()) {
+            return Ok(z2);
+        }
+
+        // the quadratic through the three points, written as a*(z - z2)^2 + b*(z - z2) + c
+        let h1 = z1 - z0;
+        let h2 = z2 - z1;
+        let d1 = (f1 - f0)/h1;
+        let d2 = (f2 - f1)/h2;
+        let a = (d2 - d1)/(h2 + h1);
+        let b = a*h2 + d2;
+        let c = f2;
+
+        // take the root of the quadratic nearest z2, i.e. the one with the larger denominator
+        let disc = (b*b - a*c*four).sqrt();
+        let den = if (b + disc).norm() >= (b - disc).norm() { b + disc } else { b - disc };
+        if den.norm() == T::zero() || !den.norm().is_finite() {
+            return Err(SolveError::DidNotConverge { best: z2 });
+        }
+        let dz = -c*two/den;
+        let z3 = z2 + dz;
+        let f3 = eval(z3)?;
+
+        // if the method has converged, return the new point
+        if dz.norm() < tol || f3.norm() < tol {
+            return Ok(z3);
+        }
+
+        z0 = z1; f0 = f1;
+        z1 = z2; f1 = f2;
+        z2 = z3; f2 = f3;
+    }
+
+    Err(SolveError::DidNotConverge { best: z2 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complex_root_of_z_squared_plus_one_from_real_points() {
+        let root = complex_solve(Complex::new(0.5, 0.0), Complex::new(2.0, 0.0), 1e-13, 100, |z: Complex<f64>| z*z + 1.0).unwrap();
+        assert!(root.re.abs() < 1e-12 && (root.im.abs() - 1.0).abs() < 1e-12, "{}", root);
+
+        // from a point in the lower half plane, the root there
+        let root = complex_solve(Complex::new(0.0, -0.5), Complex::new(1.0, -2.0), 1e-13, 100, |z: Complex<f64>| z*z + 1.0).unwrap();
+        assert!((root - Complex::new(0.0, -1.0)).norm() < 1e-12, "{}", root);
+    }
+
+    #[test]
+    fn complex_solve_gives_up_after_max_iters() {
+        // the quadratic through three points of z^2 + 1 is z^2 + 1 itself, so use a cubic
+        let result = complex_solve(Complex::new(0.5, 0.0), Complex::new(2.0, 0.0), 1e-13, 2, |z: Complex<f64>| z*z*z + 1.0);
+        assert!(matches!(result, Err(SolveError::DidNotConverge { .. })), "{:?}", result);
+    }
+}
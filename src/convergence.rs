@@ -0,0 +1,64 @@
+use num_traits::Float;
+
+/** A rule for deciding when the bracket [x0, x1], with f0 = f(x0) and f1 = f(x1), is good enough to stop.
+  * A Solver is itself a criterion, stopping when either the width or the residual is within its tolerances.
+  */
+pub trait Converged<T> {
+    fn is_converged(&self, x0: T, x1: T, f0: T, f1: T) -> bool;
+}
+
+/** Stop once the width of the bracket is below the tolerance, whatever the residual */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WidthTolerance<T = f64>(pub T);
+
+/** Stop once min(|f0|, |f1|) is below the tolerance, however wide the bracket still is */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResidualTolerance<T = f64>(pub T);
+
+/** Stop only once both the width and the residual are below their tolerances */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BothTolerances<T = f64> {
+    pub width: T,
+    pub residual: T
+}
+
+impl<T: Float> Converged<T> for WidthTolerance<T> {
+    fn is_converged(&self, x0: T, x1: T, _f0: T, _f1: T) -> bool {
+        (x1 - x0).abs() < self.0
+    }
+}
+
+impl<T: Float> Converged<T> for ResidualTolerance<T> {
+    fn is_converged(&self, _x0: T, _x1: T, f0: T, f1: T) -> bool {
+        f0.abs().min(f1.abs()) < self.0
+    }
+}
+
+impl<T: Float> Converged<T> for BothTolerances<T> {
+    fn is_converged(&self, x0: T, x1: T, f0: T, f1: T) -> bool {
+        WidthTolerance(self.width).is_converged(x0, x1, f0, f1) && ResidualTolerance(self.residual).is_converged(x0, x1, f0, f1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Solver;
+
+    #[test]
+    fn width_and_residual_criteria_stop_at_different_points() {
+        // steep, so a narrow bracket still has a large residual
+        let func = |x: f64| (1e6*(x - 0.3)).atan();
+        let deriv = |x: f64| 1e6/(1.0 + 1e12*(x - 0.3)*(x - 0.3));
+        let root = 0.3;
+
+        let by_width = Solver::new().solve_until(0.0, 1.0, func, deriv, &WidthTolerance(1e-3)).unwrap();
+        let by_residual = Solver::new().solve_until(0.0, 1.0, func, deriv, &ResidualTolerance(1e-3)).unwrap();
+        // within 1e-3 of the root, but far from it on the scale of the residual
+        assert!((by_width - root).abs() < 1e-3);
+        assert!(func(by_width).abs() > 0.1);
+        // |f| < 1e-3 needs x within about 1e-9 of the root
+        assert!(func(by_residual).abs() < 1e-3 && (by_residual - root).abs() < 1e-8);
+        assert!((by_width - by_residual).abs() > 1e-5);
+    }
+}
@@ -0,0 +1,218 @@
+/*! Cubic Hermite interpolation between two points with known values and derivatives */
+
+use num_traits::Float;
+
+use crate::{constant, safe_div};
+
+/** The cubic Hermite basis [h00, h10, h01, h11] at t, for building splines of one's own. A cubic on [0, 1] with
+  * values p0, p1 and slopes (with respect to t) m0, m1 at the ends is p0*h00 + m0*h10 + p1*h01 + m1*h11, so the basis
+  * is [1, 0, 0, 0] at t = 0 and [0, 0, 1, 0] at t = 1.
+  */
+pub fn hermite_basis<T: Float>(t: T) -> [T; 4] {
+     let two: T = constant(2.0);
+     let three: T = constant(3.0);
+
+     let h00 = two*t*t*t - three*t*t + T::one();
+     let h10 = t*t*t - two*t*t + t;
+     let h01 = -two*t*t*t + three*t*t;
+     let h11 =  t*t*t - t*t;
+
+     [h00, h10, h01, h11]
+}
+
+/** The cubic Hermite polynomial on t in [0, 1] with values p0, p1 and slopes (with respect to t) m0, m1 at the ends */
+fn hermite<T: Float>(t: T, p0: T, m0: T, p1: T, m1: T) -> T {
+     let [h00, h10, h01, h11] = hermite_basis(t);
+
+     p0*h00 + m0*h10 + p1*h01 + m1*h11
+}
+
+/** Evaluate the  cubic that matches f(x0), f'(x0), f(x1),  and f'(x1) at the value x.
+  *
+  * x0 and x1 are the two interpolation points, f0 and f1 the function values there, and df0 and df1 the
+  * derivatives there. x may lie anywhere, although the cubic is only a good approximation for x between x0 and x1.
+  * If f is itself a polynomial of degree at most 3, the cubic is f, so the result is exact up to rounding. This
+  * requires x0 != x1, as the cubic isn't defined on a single point, and the result is NaN otherwise.
+  *
+  * The cubic takes the values f0 and f1 at the ends, with slopes df0 and df1 there:
+  * ```
+  * use inverse_cubic_interpolation::interpolation::two_point_cubic;
+  *
+  * let (x0, x1, f0, df0, f1, df1) = (1.0, 3.0, 2.0, -1.0, 5.0, 4.0f64);
+  * let p = |x| two_point_cubic(x, x0, x1, f0, df0, f1, df1);
+  * assert!((p(x0) - f0).abs() < 1e-12);
+  * assert!((p(x1) - f1).abs() < 1e-12);
+  *
+  * // the slopes, by central differences
+  * let h = 1e-6;
+  * assert!(((p(x0 + h) - p(x0 - h))/(2.0*h) - df0).abs() < 1e-6);
+  * assert!(((p(x1 + h) - p(x1 - h))/(2.0*h) - df1).abs() < 1e-6);
+  * ```
+  */
+pub fn two_point_cubic<T: Float>(x: T, x0: T, x1: T, f0: T, df0: T, f1: T, df1: T) -> T{
+
+     let h: T = x1 - x0;
+     let t: T = (x - x0)/h;
+
+     // the derivative terms are scaled by the width since d/dx = (1/h) d/dt
+     hermite(t, f0, h*df0, f1, h*df1)
+}
+
+/** The coefficients [a, b, c, d] of the cubic from two_point_cubic in the monomial basis, a + b*x + c*x^2 + d*x^3,
+  * on the original x scale, e.g. for evaluating it many times or for analysing it symbolically.
+  *
+  * two_point_cubic itself doesn't use these, as the monomial form loses precision to cancellation when the
+  * interval is far from the origin compared to its width.
+  */
+pub fn cubic_coeffs<T: Float>(x0: T, x1: T, f0: T, df0: T, f1: T, df1: T) -> [T; 4] {
+     let two: T = constant(2.0);
+     let three: T = constant(3.0);
+
+     // first in powers of u = x - x0, where u = h*t in the Hermite form
+     let h = x1 - x0;
+     let e0 = f0;
+     let e1 = df0;
+     let e2 = (three*(f1 - f0)/h - two*df0 - df1)/h;
+     let e3 = (two*(f0 - f1)/h + df0 + df1)/(h*h);
+
+     // then expand (x - x0)^k
+     let s = x0;
+     [e0 - e1*s + e2*s*s - e3*s*s*s,
+      e1 - two*e2*s + three*e3*s*s,
+      e2 - three*e3*s,
+      e3]
+}
+
+/** The same as two_point_cubic, except the inverse of f(x) is approximated, and evaluated at target. The result is an
+  * estimate of the x where f(x) = target.
+  *
+  * The parameters otherwise have the same meaning as for two_point_cubic. This requires f0 != f1 and nonzero
+  * derivatives at both points.
+  *
+  * The estimate is exact, up to rounding, when the inverse of f is a polynomial of degree at most 3, i.e. when
+  * f is the inverse of a monotone cubic such as x = 1 + 2y + y^2/2 + y^3/10 on the bracket. Otherwise the error is
+  * of the order of the fourth derivative of the inverse times (f1 - f0)^4, which is what makes the solver converge
+  * so quickly once the bracket is small.
+  */
+pub fn two_point_cubic_inverse_at<T: Float>(target: T, x0: T, x1: T, f0: T, df0: T, f1: T, df1: T) -> T{
+     // the inverse runs from (f0, x0) to (f1, x1) with slopes 1/df0 and 1/df1. Rather than forming the reciprocals,
+     // which overflow for tiny derivatives and lose precision for huge ones, the slopes are scaled by the width
+     // f1 - f0 in a single division.
+     let h = f1 - f0;
+     let t = (target - f0)/h;
+
+     hermite(t, x0, h/df0, x1, h/df1)
+}
+
+/** two_point_cubic_inverse_at, or None if f0 == f1, a derivative is zero, or the slopes of the inverse overflow,
+  * rather than inf or NaN
+  */
+pub(crate) fn checked_cubic_inverse_at<T: Float>(target: T, x0: T, x1: T, f0: T, df0: T, f1: T, df1: T) -> Option<T> {
+     let h = f1 - f0;
+     let t = safe_div(target - f0, h)?;
+
+     Some(hermite(t, x0, safe_div(h, df0)?, x1, safe_div(h, df1)?))
+}
+
+/** The estimate of the root of f(x) from the inverse cubic, i.e. two_point_cubic_inverse_at with a target of 0.
+  *
+  * The parameters have the same meaning as for two_point_cubic, and the same requirements as for
+  * two_point_cubic_inverse_at apply.
+  */
+pub fn two_point_cubic_inverse<T: Float> ( x0: T, x1: T, f0: T, df0: T, f1: T, df1: T) -> T{
+     two_point_cubic_inverse_at(T::zero(), x0, x1, f0, df0, f1, df1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inverse_survives_a_derivative_whose_reciprocal_overflows() {
+        // 1/c is inf, but the slopes of the inverse scaled by the width are 1
+        let c = 1e-310;
+        let x = two_point_cubic_inverse(0.0, 1.0, -0.5*c, c, 0.5*c, c);
+        assert!((x - 0.5).abs() < 1e-6, "{}", x);
+    }
+
+    #[test]
+    fn checked_inverse_rejects_slopes_that_overflow() {
+        assert_eq!(checked_cubic_inverse_at(0.0, 0.0, 1.0, -1.0, 1e-310, 1.0, 1.0), None);
+        assert_eq!(checked_cubic_inverse_at(0.0, 0.0, 1.0, -1.0, 0.0, 1.0, 1.0), None);
+        assert_eq!(checked_cubic_inverse_at(0.0, 0.0, 1.0, 1.0, 1.0, 1.0, 1.0), None);
+    }
+
+    #[test]
+    fn inverse_at_a_target_approximates_the_analytic_inverse() {
+        // exp on [0, 0.5], whose inverse is ln
+        let e = 0.5f64.exp();
+        let inverse = |target: f64| two_point_cubic_inverse_at(target, 0.0, 0.5, 1.0, 1.0, e, e);
+        assert_eq!((inverse(1.0), inverse(e)), (0.0, 0.5));
+        for &target in &[1.1, 1.2, 1.4, 1.6] {
+            assert!((inverse(target) - f64::ln(target)).abs() < 2e-3, "{} vs {}", inverse(target), f64::ln(target));
+        }
+        // the root of exp(x) - 1.2 is the inverse at 1.2
+        let root = two_point_cubic_inverse(0.0, 0.5, 1.0 - 1.2, 1.0, e - 1.2, e);
+        assert!((root - inverse(1.2)).abs() < 1e-15);
+    }
+
+    #[test]
+    fn inverse_is_exact_when_the_inverse_is_a_cubic() {
+        // f is the inverse of x = p(y) = 1 + 2y + y^2/2 + y^3/10, which is monotone, so f(x) = 0 at x = p(0) = 1
+        let p = |y: f64| 1.0 + 2.0*y + y*y/2.0 + y*y*y/10.0;
+        let dp = |y: f64| 2.0 + y + 0.3*y*y;
+        let (y0, y1) = (-1.0, 2.0);
+        let (x0, x1) = (p(y0), p(y1));
+        let (df0, df1) = (1.0/dp(y0), 1.0/dp(y1));
+
+        let x = two_point_cubic_inverse(x0, x1, y0, df0, y1, df1);
+        assert!((x - 1.0).abs() <= 4.0*f64::EPSILON, "{}", x);
+        for &target in &[-0.5, 0.25, 1.5] {
+            let x = two_point_cubic_inverse_at(target, x0, x1, y0, df0, y1, df1);
+            assert!((x - p(target)).abs() <= 4.0*f64::EPSILON*p(target).abs(), "{} vs {}", x, p(target));
+        }
+    }
+
+    #[test]
+    fn hermite_basis_at_the_ends() {
+        assert_eq!(hermite_basis(0.0), [1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(hermite_basis(1.0), [0.0, 0.0, 1.0, 0.0]);
+        // the value basis functions sum to one everywhere
+        for &t in &[0.1, 0.5, 0.9, 2.0] {
+            let [h00, _, h01, _] = hermite_basis(t);
+            assert!((h00 + h01 - 1.0).abs() < 1e-15, "{}", t);
+        }
+        // and two_point_cubic is built on them
+        let (x0, x1, f0, df0, f1, df1) = (1.0, 3.0, 2.0, -1.0, 5.0, 4.0);
+        let [h00, h10, h01, h11] = hermite_basis(0.25);
+        assert_eq!(two_point_cubic(1.5, x0, x1, f0, df0, f1, df1), f0*h00 + 2.0*df0*h10 + f1*h01 + 2.0*df1*h11);
+    }
+
+    #[test]
+    fn coefficients_reproduce_the_ends_and_the_hermite_form() {
+        // away from the origin, so the expansion of (x - x0)^k cancels
+        let (x0, x1, f0, df0, f1, df1) = (2.0, 3.0, 0.5, -1.5, 2.0, 4.0f64);
+        let [a, b, c, d] = cubic_coeffs(x0, x1, f0, df0, f1, df1);
+        let p = |x: f64| a + b*x + c*x*x + d*x*x*x;
+        let dp = |x: f64| b + 2.0*c*x + 3.0*d*x*x;
+        let close = |x: f64, y: f64| (x - y).abs() <= 1e-12*y.abs().max(1.0);
+
+        assert!(close(p(x0), f0), "{} vs {}", p(x0), f0);
+        assert!(close(dp(x0), df0), "{} vs {}", dp(x0), df0);
+        assert!(close(p(x1), f1), "{} vs {}", p(x1), f1);
+        assert!(close(dp(x1), df1), "{} vs {}", dp(x1), df1);
+        let x = 2.3;
+        assert!(close(p(x), two_point_cubic(x, x0, x1, f0, df0, f1, df1)), "{}", p(x));
+    }
+
+    #[test]
+    fn cubic_on_a_single_point_is_nan() {
+        // t = (x - x0)/(x1 - x0) divides by zero, for x at the point and away from it
+        assert!(two_point_cubic(1.0, 1.0, 1.0, 2.0, 3.0, 2.0, 3.0f64).is_nan());
+        assert!(two_point_cubic(1.5, 1.0, 1.0, 2.0, 3.0, 2.0, 3.0f64).is_nan());
+        // while the narrowest bracket there is still gives the values at its ends
+        let x1 = 1.0 + f64::EPSILON;
+        assert_eq!(two_point_cubic(1.0, 1.0, x1, 2.0, 3.0, 4.0, 3.0), 2.0);
+        assert_eq!(two_point_cubic(x1, 1.0, x1, 2.0, 3.0, 4.0, 3.0), 4.0);
+    }
+}
@@ -1,183 +1,149 @@
-use std::f64;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use core::fmt;
+use num_traits::Float;
+
+pub mod solver;
+pub mod interpolation;
+pub mod brent;
+pub mod muller;
+pub mod secant;
+pub mod convergence;
+pub mod predictor;
+pub mod quantity;
+#[cfg(feature = "std")]
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod spline;
+#[cfg(feature = "std")]
+pub mod subdivision;
+#[cfg(feature = "std")]
+pub mod memoized;
+#[cfg(feature = "complex")]
+pub mod complex;
+#[cfg(feature = "async")]
+pub mod async_solve;
+#[cfg(feature = "dashu")]
+pub mod big;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod prelude;
+
+pub use solver::*;
 
 const BISECTION_FREQ : usize = 5;
-
-#[derive(PartialEq)]
-enum CoordinateChangeFlag {
-   First,
-   Second,
-   Reset
+const DEFAULT_MAX_ITERS : usize = 1000;
+const BRACKET_GROWTH : f64 = 1.6;
+const DEFAULT_MAX_EXPANSIONS : usize = 50;
+// the first step of Solver::solve_monotone is at most this many times max(|guess|, 1)
+const MONOTONE_MAX_FIRST_STEP : f64 = 100.0;
+// the default of Solver::suspicious_brackets, how many brackets in a row with derivatives contradicting the secant
+// are allowed before the derivative is suspicious
+const SUSPICIOUS_BRACKETS : usize = 3;
+
+/** The ways in which the solver can fail */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SolveError<T = f64> {
+   /// f(x0) and f(x1) do not have opposite signs, so the interval does not bracket a root
+   NonBracketingInterval,
+   /// the function returned NaN or an infinite value at x, e.g. because of a pole inside the bracket
+   NonFiniteFunctionValue { x: T },
+   /// the method did not reach the requested tolerance within the maximum number of iterations,
+   /// `best` is the best point found before giving up
+   DidNotConverge { best: T },
+   /// the best point moved by less than the stagnation tolerance for too many iterations in a row, see
+   /// Solver::stagnation. `best` is the best point found.
+   Stagnated { best: T },
+   /// the solve was cancelled, see Solver::solve_cancellable. `best` is the best point found before then.
+   Cancelled { best: T },
+   /// another step would take more evaluations of the function than allowed, see Solver::func_eval_budget. `best` is
+   /// the best point found within the budget.
+   BudgetExhausted { best: T },
+   /// the ends of the bracket are adjacent floating point numbers, so it can't shrink any further, but the tolerance
+   /// still isn't met, e.g. because it is smaller than the spacing of the floats near the root. `best` is the better
+   /// end, which is as close to the root as the floating point type allows.
+   MachinePrecisionReached { best: T },
+   /// the solve took longer than allowed, see Solver::timeout. `best` is the best point found before then.
+   TimedOut { best: T },
+   /// the derivative given at x disagrees with a central difference estimate, see Solver::check_derivatives
+   DerivativeMismatch { x: T, analytic: T, numeric: T },
+   /// the inputs to a function solving several problems at once, e.g. the brackets and functions of a system, are not
+   /// all of the same length
+   MismatchedLengths
 }
 
-/** Standard bisection method */
-fn naive_bisection (x0: f64, x1: f64, f0: f64, f1: f64,  func: fn(f64) -> f64) -> (f64, f64){
-
-   let x_new = (x0 + x1)/2.0; 
-   let f_new = func(x_new);
-   
-   if f_new * f0 <= 0.0{
-       (x0, x_new)
-   }
-   else if f_new * f1 <= 0.0 {
-       (x_new, x1)
-   }
-   else {
-        panic!("Bisection Failure");
+impl<T> SolveError<T> {
+   /** The same error with the points in it, x and best, mapped by f, e.g. from the variable the solver worked in back
+     * to the caller's. The derivatives in DerivativeMismatch are left as they are.
+     */
+   pub(crate) fn map_points(self, f: impl Fn(T) -> T) -> SolveError<T> {
+       match self {
+           SolveError::NonFiniteFunctionValue { x } => SolveError::NonFiniteFunctionValue { x: f(x) },
+           SolveError::DidNotConverge { best } => SolveError::DidNotConverge { best: f(best) },
+           SolveError::Stagnated { best } => SolveError::Stagnated { best: f(best) },
+           SolveError::Cancelled { best } => SolveError::Cancelled { best: f(best) },
+           SolveError::BudgetExhausted { best } => SolveError::BudgetExhausted { best: f(best) },
+           SolveError::MachinePrecisionReached { best } => SolveError::MachinePrecisionReached { best: f(best) },
+           SolveError::TimedOut { best } => SolveError::TimedOut { best: f(best) },
+           SolveError::DerivativeMismatch { x, analytic, numeric } => SolveError::DerivativeMismatch { x: f(x), analytic, numeric },
+           SolveError::NonBracketingInterval => SolveError::NonBracketingInterval,
+           SolveError::MismatchedLengths => SolveError::MismatchedLengths
+       }
    }
 }
 
-fn false_position (x0: f64, x1: f64, f0: f64, f1: f64,  func: fn(f64) -> f64) -> (f64, f64){
-
-   let x_new = (x0*f1 - x1*f0)/(f1 - f0); 
-   let f_new = func(x_new);
-   
-   if f_new * f0 <= 0.0{
-       return (x0, x_new);
-   }
-   else if f_new * f1 <= 0.0 {
-       return (x_new, x1);
-   }
-   else {
-        panic!("Bisection Failure");
+impl<T: fmt::Display> fmt::Display for SolveError<T> {
+   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+       match self {
+           SolveError::NonBracketingInterval => write!(f, "interval does not bracket a root"),
+           SolveError::NonFiniteFunctionValue { x } => write!(f, "function returned a non-finite value at x = {}", x),
+           SolveError::DidNotConverge { best } => write!(f, "solver did not converge, best point found was {}", best),
+           SolveError::Stagnated { best } => write!(f, "solver stagnated at {}", best),
+           SolveError::Cancelled { best } => write!(f, "solve was cancelled, best point found was {}", best),
+           SolveError::BudgetExhausted { best } => write!(f, "function evaluation budget exhausted, best point found was {}", best),
+           SolveError::MachinePrecisionReached { best } => write!(f, "bracket can't shrink any further at {}, the tolerance is too small", best),
+           SolveError::TimedOut { best } => write!(f, "solve timed out, best point found was {}", best),
+           SolveError::DerivativeMismatch { x, analytic, numeric } =>
+               write!(f, "derivative at x = {} is {}, but a finite difference gives {}", x, analytic, numeric),
+           SolveError::MismatchedLengths => write!(f, "inputs are not all of the same length")
+       }
    }
 }
 
+#[cfg(feature = "std")]
+impl<T: fmt::Debug + fmt::Display> std::error::Error for SolveError<T> {}
 
-/** Evaluate the  cubic that matches f(x0), f'(x0), f(x1),  and f'(x1) at the value x */
-fn two_point_cubic(x: f64, x0: f64, x1: f64, f0: f64, df0: f64, f1: f64, df1: f64) -> f64{
-
-     let t: f64 = (x - x0)/(x1 - x0);
-
-     let h00 = 2.0*t*t*t - 3.0*t*t + 1.0;
-     let h10 = t*t*t - 2.0*t*t + t;
-     let h01 = -2.0*t*t*t + 3.0*t*t;
-     let h11 =  t*t*t - t*t;
-
-     f0*h00 + df0*h10 + f1*h01 + df1*h11
-}
-
-/** The same as two_point_cubic, except the inverse of f(x) is approximated, and evaluated at 0 */
-fn two_point_cubic_inverse ( x0: f64, x1: f64, f0: f64, df0: f64, f1: f64, df1: f64) -> f64{
-     let y0 = f0;
-     let y1 = f1;
- 
-     let g0 = x0;
-     let g1 = x1;
-       
-     let dg0 = 1.0/df0;
-     let dg1 = 1.0/df1;
-
-     two_point_cubic(0.0, y0, y1, g0, dg0, g1, dg1)
+/** Convert an f64 constant into the working float type. This can't fail for f32 and f64, or any type whose
+  * NumCast conversion from f64 is total, which is all the solver assumes of T beyond Float.
+  */
+fn constant<T: Float>(c: f64) -> T {
+   T::from(c).unwrap()
 }
 
-/** Given points x0 and x1 such that f(x0)*f(x1) < 0, use cubic interpolation to find a point between that is an approximate 
-  * root of f(x).
+/** num/den, or None if den is zero or infinite, or the quotient isn't finite, e.g. because den is so small that it
+  * overflows. For the steps built on a division, so a degenerate bracket falls back to another step rather than
+  * handing on inf or NaN.
   */
-fn cubic_bisection (x0: f64, x1: f64, f0: f64, df0:f64,  f1: f64, df1: f64,  func: fn(f64) -> f64) -> Result<(f64, f64), f64>{
-   let x_new = two_point_cubic_inverse(x0, x1, f0, df0, f1, df1);
-
-   if x_new <= x0 || x_new >= x1 {
-       Err(std::f64::NAN)
-   } 
-   else {
-       let f_new = func(x_new);
-   
-       if f_new * f0 <= 0.0{
-           Ok((x0, x_new))
-       }
-       else if f_new * f1 <= 0.0{
-           Ok((x_new, x1))
-       }
-       else { 
-           panic!("Bisection Failure"); // this condition should never happen in theory
-       }
-   }
-}  
-
-/** The actual solver */
-pub fn inv_cubic_solve(x0: f64, x1: f64, tol: f64, func: fn(f64) -> f64, deriv: fn(f64) -> f64) ->  f64{
-
-    let mut x_best;
-    let mut x  = (x0, x1);
-    let mut dx  = (x0 - x1).abs();
-
-    // function values
-    let mut f0 = func(x0);
-    let mut f1 = func(x1);
-
-    // derivatives
-    let mut df0 = deriv(x0);
-    let mut df1 = deriv(x1);
-
-    let mut last_coord_changed =  CoordinateChangeFlag::Reset;
-    let mut should_bisect  = false;
-
-    // number of iterations
-    let mut n_iters :usize = 1;
-
-    /* 
-       NB: The loop below will terminate so long as f(x0) and f(x1) are of opposite sign.  In the worst case, the width will halve every BISECTION_FREQ iterations
-    */
-    loop{
-
-        // temporarily store the current values
-        let x_old = x;
-
-        let f_max = f0.abs().max(f1.abs());
-        let f_min = f0.abs().min(f1.abs());
-
-        // print data
-        println!("{0:0<02} x1 = {1:0<022.19} x2= {2:0<022.19}  min(|f(x1)|, |f(x2)|) = {3:0<022.19}  max(|f(x1)|, |f(x2)|) = {4:0<024.19} log10(|x2 - x1|) = {5:0<+012.10}",
-                        n_iters, x.0 , x.1, f_min, f_max, dx.abs().log10());
-
-        // get the best point found so far
-        x_best =  if f0.abs() < f1.abs() {x.0} else {x.1};
-
-        // if the method has converged, return the best point
-        if dx.abs() < tol || f_min < tol {
-            return x_best;
-        }
-              
-        // perform bisection every nth iteration, or if a point hasn't been changed in two iterations.
-        if n_iters % BISECTION_FREQ  == 0 || should_bisect {
-            x = naive_bisection(x.0, x.1, f0, f1, func);
-            should_bisect = false;
-            // reset the flag to tell if a point hasn't changed in two iterations
-            last_coord_changed = CoordinateChangeFlag::Reset; 
-        } else {
-            let result = cubic_bisection(x.0, x.1, f0, df0,  f1, df1, func);
-            x = match result{
-               Ok(_x) => _x,
-               Err(_) => false_position(x.0, x.1, f0, f1, func)
-           };      
-        }
-
-        // update the function values and derivatives depending on which point was changed
-        // and determine if the same point was changed twice in a row
-        let second_last_coord_changed = last_coord_changed;
-        if x_old.0 != x.0 {
-              f0 = func(x.0);
-             df0 = deriv(x.0);
-             last_coord_changed = CoordinateChangeFlag::First;
-        } else {
-              f1 = func(x.1);
-             df1 = deriv(x.1);
-             last_coord_changed = CoordinateChangeFlag::Second;
-        };
-        
-        if last_coord_changed == second_last_coord_changed{
-            should_bisect = true;
-        }
-
-        // update various statistics
-        n_iters = n_iters + 1;
-        dx = (x.1 - x.0).abs();
-
-    }   
+fn safe_div<T: Float>(num: T, den: T) -> Option<T> {
+   let quotient = num/den;
+   if den != T::zero() && den.is_finite() && quotient.is_finite() { Some(quotient) } else { None }
 }
 
 // Let's test it out!!!
 
-
-
-
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn safe_div_rejects_zero_infinite_and_overflowing_denominators() {
+        assert_eq!(safe_div(1.0, 4.0), Some(0.25));
+        assert_eq!(safe_div(1.0, 0.0), None);
+        assert_eq!(safe_div(1.0, -0.0), None);
+        assert_eq!(safe_div(1.0, f64::INFINITY), None);
+        assert_eq!(safe_div(1.0, f64::NAN), None);
+        assert_eq!(safe_div(1.0, 1e-310), None);
+        assert_eq!(safe_div(f64::NAN, 1.0), None);
+        assert_eq!(safe_div(0.0, 1e-310), Some(0.0));
+    }
+}
@@ -1,12 +1,24 @@
-mod lib;
-use lib::inv_cubic_solve;
+use inverse_cubic_interpolation::{inv_cubic_solve_with_callback, IterationInfo};
+use num_traits::Float;
 
-// function for which we want to find the root and its derivative
-fn func(x: f64)  -> f64{x.sin() + x.powi(3) }
-fn dfunc(x: f64) -> f64{x.cos() + 3.0*x.powi(2) }
+// function for which we want to find the root and its derivative. These go through num_traits::Float, which falls back
+// on libm without std, so they can be copied as they are into a no_std program.
+fn func(x: f64)  -> f64{Float::sin(x) + Float::powi(x, 3) }
+fn dfunc(x: f64) -> f64{Float::cos(x) + 3.0*Float::powi(x, 2) }
+
+// print the state of the solver at each iteration
+fn print_iteration(info: &IterationInfo){
+   let log10_width = match info.log10_width() {
+       Some(l) => format!("{:0<+012.10}", l),
+       None => String::from("none (x1 == x2)")
+   };
+   println!("{0:0<02} x1 = {1:0<022.19} x2= {2:0<022.19}  min(|f(x1)|, |f(x2)|) = {3:0<022.19}  max(|f(x1)|, |f(x2)|) = {4:0<024.19} log10(|x2 - x1|) = {5}",
+            info.n_iters, info.x0, info.x1, info.f_min, info.f_max, log10_width);
+}
 
 fn main(){
-   let root =  inv_cubic_solve(-1.0, 0.5, 1e-14, func, dfunc);
-   println!("\nroot = {}\nf(root) = {}\nlog10(|f(root)|) = {}", root, func(root), func(root).abs().log10());
+   match inv_cubic_solve_with_callback(-1.0, 0.5, 1e-14, 100, func, dfunc, print_iteration) {
+       Ok(root) => println!("\nroot = {}\nf(root) = {}\nlog10(|f(root)|) = {}", root, func(root), Float::log10(Float::abs(func(root)))),
+       Err(e) => println!("\nsolver failed: {}", e)
+   }
 }
-           
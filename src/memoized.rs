@@ -0,0 +1,73 @@
+/*! Caching the values of an expensive function, so evaluating it again at the same point is free */
+
+use core::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use num_traits::Float;
+
+/** A function with a cache of every value it has returned, keyed by the exact bits of x, so only identical points are
+  * shared and -0.0 is a different point from 0.0. The solver takes functions rather than a trait, so it's passed as
+  * `|x| memo.call(x)`, and one wrapper can be kept for func and another for deriv. The cache is never cleared, which
+  * is fine for a few solves but grows without bound for many, and isn't thread safe.
+  */
+#[derive(Debug)]
+pub struct Memoized<T, F> {
+    func: F,
+    cache: RefCell<HashMap<(u64, i16, i8), T>>,
+    hits: Cell<usize>
+}
+
+impl<T: Float, F: Fn(T) -> T> Memoized<T, F> {
+    /** Wrap func with an empty cache, which then holds func(x) for every x it's called at, keyed by the bits of x */
+    pub fn new(func: F) -> Self {
+        Memoized { func, cache: RefCell::new(HashMap::new()), hits: Cell::new(0) }
+    }
+
+    /** func(x), from the cache if x has been seen before */
+    pub fn call(&self, x: T) -> T {
+        let key = x.integer_decode();
+        if let Some(&value) = self.cache.borrow().get(&key) {
+            self.hits.set(self.hits.get() + 1);
+            return value;
+        }
+        let value = (self.func)(x);
+        self.cache.borrow_mut().insert(key, value);
+        value
+    }
+
+    /** How many calls were answered from the cache */
+    pub fn hits(&self) -> usize {
+        self.hits.get()
+    }
+
+    /** How many distinct points func has been evaluated at */
+    pub fn evaluations(&self) -> usize {
+        self.cache.borrow().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Solver;
+
+    #[test]
+    fn repeated_points_hit_the_cache() {
+        let evals = Cell::new(0);
+        let memo = Memoized::new(|x: f64| { evals.set(evals.get() + 1); x.sin() + x.powi(3) - 0.5 });
+        assert_eq!(memo.call(1.5), memo.call(1.5));
+        assert_eq!((evals.get(), memo.hits(), memo.evaluations()), (1, 1, 1));
+
+        // -0.0 has different bits from 0.0
+        memo.call(0.0);
+        memo.call(-0.0);
+        assert_eq!((evals.get(), memo.hits()), (3, 1));
+
+        // solving the same problem again evaluates nothing new
+        let solver = Solver::new().tol(1e-12);
+        let root = solver.solve(-1.0, 2.0, |x| memo.call(x), |x: f64| x.cos() + 3.0*x*x).unwrap();
+        let (evals_once, hits_once) = (evals.get(), memo.hits());
+        assert_eq!(solver.solve(-1.0, 2.0, |x| memo.call(x), |x: f64| x.cos() + 3.0*x*x).unwrap(), root);
+        assert_eq!(evals.get(), evals_once);
+        assert_eq!(memo.hits() - hits_once, evals_once - 3);
+    }
+}
@@ -0,0 +1,90 @@
+/*! Muller's method, which fits a quadratic through the last three points instead of a cubic through the ends of a bracket */
+
+use num_traits::Float;
+
+use crate::{constant, SolveError};
+
+/** Find a root of func by Muller's method, starting from the three points x0, x1 and x2. Only function values are
+  * needed, and the points don't have to bracket a root. The method has converged once the step or the residual is
+  * below tol, and gives up with DidNotConverge after max_iters iterations.
+  *
+  * Muller's method can also approach complex roots, but that would need a Complex return type, so here only real
+  * roots are looked for: if the quadratic has no real roots, the solver steps to its turning point, which is the real
+  * part of the complex step.
+  */
+pub fn muller_solve<T, F>(x0: T, x1: T, x2: T, tol: T, max_iters: usize, func: F) -> Result<T, SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> T
+{
+    let two: T = constant(2.0);
+    let four: T = constant(4.0);
+
+    let eval = |x: T| {
+        let f = func(x);
+        if f.is_finite() { Ok(f) } else { Err(SolveError::NonFiniteFunctionValue { x }) }
+    };
+
+    let (mut x0, mut x1, mut x2) = (x0, x1, x2);
+    let (mut f0, mut f1, mut f2) = (eval(x0)?, eval(x1)?, eval(x2)?);
+
+    for _ in 0..max_iters {
+        if f2 == T::zero() {
+            return Ok(x2);
+        }
+
+        // the quadratic through the three points, written as a*(x - x2)^2 + b*(x - x2) + c
+        let h1 = x1 - x0;
+        let h2 = x2 - x1;
+        let d1 = (f1 - f0)/h1;
+        let d2 = (f2 - f1)/h2;
+        let a = (d2 - d1)/(h2 + h1);
+        let b = a*h2 + d2;
+        let c = f2;
+
+        // take the root of the quadratic nearest x2, i.e. the one with the larger denominator
+        let disc = (b*b - four*a*c).max(T::zero());
+        let den = if b < T::zero() { b - disc.sqrt() } else { b + disc.sqrt() };
+        if den == T::zero() || !den.is_finite() {
+            return Err(SolveError::DidNotConverge { best: x2 });
+        }
+        let dx = -two*c/den;
+        let x3 = x2 + dx;
+        let f3 = eval(x3)?;
+
+        // if the method has converged, return the new point
+        if dx.abs() < tol || f3.abs() < tol {
+            return Ok(x3);
+        }
+
+        x0 = x1; f0 = f1;
+        x1 = x2; f1 = f2;
+        x2 = x3; f2 = f3;
+    }
+
+    Err(SolveError::DidNotConverge { best: x2 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn muller_finds_a_real_root() {
+        // the real root of x^3 - 2x - 5, Wallis's example
+        let root = muller_solve(1.0, 2.0, 3.0, 1e-13, 100, |x: f64| x.powi(3) - 2.0*x - 5.0).unwrap();
+        assert!((root - 2.0945514815423265).abs() < 1e-12);
+
+        // the points needn't bracket the root, here they are all below it
+        let root = muller_solve(0.0, 0.1, 0.2, 1e-13, 100, |x: f64| x.cos() - x).unwrap();
+        assert!((root.cos() - root).abs() < 1e-12);
+    }
+
+    #[test]
+    fn muller_gives_up_after_max_iters() {
+        let evals = core::cell::Cell::new(0);
+        let result = muller_solve(0.0, 0.1, 0.2, 1e-13, 2, |x: f64| { evals.set(evals.get() + 1); x.cos() - x });
+        assert!(matches!(result, Err(SolveError::DidNotConverge { .. })), "{:?}", result);
+        assert_eq!(evals.get(), 5);
+    }
+}
@@ -0,0 +1,82 @@
+/*! The interpolation step of the solver, which can be swapped for one's own with Solver::solve_with_predictor */
+
+use num_traits::Float;
+
+use crate::interpolation::checked_cubic_inverse_at;
+use crate::solver::{reliable_derivatives, BracketState};
+
+/** A guess at the root of f from the bracket [x0, x1], with the function values f0, f1 and the derivatives df0, df1
+  * at its ends. None rejects the bracket, and the solver falls back to its other steps. A guess outside the bracket is
+  * rejected by the solver as well, so a predictor need not check for that itself. The derivatives are NaN if nothing
+  * else in the solve needs them, i.e. if the predictor is only used through a Solver with cubic turned off.
+  */
+pub trait Predictor<T> {
+    fn predict(&self, x0: T, x1: T, f0: T, df0: T, f1: T, df1: T) -> Option<T>;
+
+    /** Called with each point the solver is about to evaluate, other than a bisection, whichever step chose it. Some(x)
+      * evaluates x instead, and None bisects. A point outside the bracket also bisects. By default every point is
+      * evaluated as it is.
+      */
+    fn accept_step(&self, x: T, _state: &BracketState<T>) -> Option<T> {
+        Some(x)
+    }
+}
+
+/** The default predictor, the root of the inverse cubic from two_point_cubic_inverse. It's rejected when either
+  * derivative is too flat compared to the secant for the inverse to be trusted, or the inverse can't be formed.
+  */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CubicPredictor;
+
+impl<T: Float> Predictor<T> for CubicPredictor {
+    fn predict(&self, x0: T, x1: T, f0: T, df0: T, f1: T, df1: T) -> Option<T> {
+        if reliable_derivatives(x0, x1, f0, df0, f1, df1) {
+            checked_cubic_inverse_at(T::zero(), x0, x1, f0, df0, f1, df1)
+        } else {
+            None
+        }
+    }
+}
+
+/** A predictor with a hook that can veto or move any step, as in Solver::solve_with_hook. The hook is called after the
+  * predictor's own accept_step.
+  */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AcceptStep<P, H> {
+    pub predictor: P,
+    pub hook: H
+}
+
+impl<T, P, H> Predictor<T> for AcceptStep<P, H>
+where
+    P: Predictor<T>,
+    H: Fn(T, &BracketState<T>) -> Option<T>
+{
+    fn predict(&self, x0: T, x1: T, f0: T, df0: T, f1: T, df1: T) -> Option<T> {
+        self.predictor.predict(x0, x1, f0, df0, f1, df1)
+    }
+
+    fn accept_step(&self, x: T, state: &BracketState<T>) -> Option<T> {
+        self.predictor.accept_step(x, state).and_then(|x| (self.hook)(x, state))
+    }
+}
+
+/** The point the inverse cubic step would try for the bracket [x0, x1], without evaluating anything, e.g. to see how
+  * good the prediction is for a given function. None if CubicPredictor rejects the bracket, or the prediction isn't
+  * strictly inside it, in which case the solver would take another kind of step.
+  *
+  * For x^2 - 2 on [1, 2] the prediction is within 0.02 of sqrt(2), but with a derivative that is nearly flat
+  * compared to the secant there is none:
+  * ```
+  * use inverse_cubic_interpolation::predictor::predict_root;
+  *
+  * let x = predict_root(1.0, 2.0, -1.0, 2.0, 2.0, 4.0).unwrap();
+  * assert!((x - 2f64.sqrt()).abs() < 0.02);
+  *
+  * assert_eq!(predict_root(1.0, 2.0, -1.0, 1e-12, 2.0, 4.0), None);
+  * ```
+  */
+pub fn predict_root<T: Float>(x0: T, x1: T, f0: T, df0: T, f1: T, df1: T) -> Option<T> {
+    let (lo, hi) = (x0.min(x1), x0.max(x1));
+    CubicPredictor.predict(x0, x1, f0, df0, f1, df1).filter(|&x| x > lo && x < hi)
+}
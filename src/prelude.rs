@@ -0,0 +1,21 @@
+/*! The items most uses of the crate need, for `use inverse_cubic_interpolation::prelude::*;`
+  *
+  * ```
+  * use inverse_cubic_interpolation::prelude::*;
+  *
+  * let func = |x: f64| x.sin() + x.powi(3) - 0.5;
+  * let deriv = |x: f64| x.cos() + 3.0*x*x;
+  * let root = inv_cubic_solve(0.0, 1.0, 1e-12, 100, func, deriv).unwrap();
+  * assert_eq!(Solver::new().tol(1e-12).solve(0.0, 1.0, func, deriv), Ok(root));
+  * assert_eq!(inv_cubic_solve(1.0, 2.0, 1e-12, 100, func, deriv), Err(SolveError::NonBracketingInterval));
+  *
+  * // the root of the inverse cubic through the ends of the bracket is a first estimate of the root
+  * let estimate = two_point_cubic_inverse(0.0, 1.0, func(0.0), deriv(0.0), func(1.0), deriv(1.0));
+  * assert!((estimate - root).abs() < 0.1);
+  * assert!(two_point_cubic(estimate, 0.0, 1.0, func(0.0), deriv(0.0), func(1.0), deriv(1.0)).abs() < 0.1);
+  * ```
+  */
+
+pub use crate::{inv_cubic_solve, Solver, SolveError};
+pub use crate::convergence::Converged;
+pub use crate::interpolation::{two_point_cubic, two_point_cubic_inverse};
@@ -0,0 +1,103 @@
+/*! Solving for quantities with units, or any other newtype around a float, without unwrapping them by hand */
+
+use num_traits::Float;
+
+use crate::{SolveError, Solver};
+
+/** A thin wrapper around a float, e.g. a length in metres or a duration in seconds. The solver works on the values,
+  * so they must be in the same units for every call, and from_value(q.value()) must give back q.
+  */
+pub trait Quantity: Copy {
+    type Value: Float;
+
+    fn value(self) -> Self::Value;
+    fn from_value(value: Self::Value) -> Self;
+}
+
+impl Quantity for f32 {
+    type Value = f32;
+
+    fn value(self) -> f32 {
+        self
+    }
+
+    fn from_value(value: f32) -> f32 {
+        value
+    }
+}
+
+impl Quantity for f64 {
+    type Value = f64;
+
+    fn value(self) -> f64 {
+        self
+    }
+
+    fn from_value(value: f64) -> f64 {
+        value
+    }
+}
+
+/** Same as inv_cubic_solve, but x, f(x) and f'(x) are quantities of their own types, all wrapping the same float, so
+  * e.g. a function from a length to a force is solved for a length. tol is a tolerance on x, so of the same type.
+  * Errors carry the underlying values, as the derivatives in DerivativeMismatch aren't of the type of x.
+  */
+pub fn inv_cubic_solve_quantity<X, Y, DY, F, D>(x0: X, x1: X, tol: X, max_iters: usize, func: F, deriv: D) -> Result<X, SolveError<X::Value>>
+where
+    X: Quantity,
+    Y: Quantity<Value = X::Value>,
+    DY: Quantity<Value = X::Value>,
+    F: Fn(X) -> Y,
+    D: Fn(X) -> DY
+{
+    let root = Solver::new().tol(tol.value()).max_iters(max_iters)
+                            .solve(x0.value(), x1.value(), |x| func(X::from_value(x)).value(), |x| deriv(X::from_value(x)).value())?;
+    Ok(X::from_value(root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Metres(f64);
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Newtons(f64);
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct NewtonsPerMetre(f64);
+
+    macro_rules! quantity {
+        ($t:ident) => {
+            impl Quantity for $t {
+                type Value = f64;
+
+                fn value(self) -> f64 {
+                    self.0
+                }
+
+                fn from_value(value: f64) -> $t {
+                    $t(value)
+                }
+            }
+        };
+    }
+
+    quantity!(Metres);
+    quantity!(Newtons);
+    quantity!(NewtonsPerMetre);
+
+    #[test]
+    fn solves_for_a_length() {
+        // the extension of a stiffening spring, F = kx + cx^3, under a load of 10 N
+        let force = |x: Metres| Newtons(50.0*x.0 + 200.0*x.0.powi(3) - 10.0);
+        let stiffness = |x: Metres| NewtonsPerMetre(50.0 + 600.0*x.0*x.0);
+        let x: Metres = inv_cubic_solve_quantity(Metres(0.0), Metres(1.0), Metres(1e-12), 100, force, stiffness).unwrap();
+        assert!(force(x).0.abs() < 1e-9, "{:?}", x);
+        assert_eq!(x.0, crate::inv_cubic_solve(0.0, 1.0, 1e-12, 100, |x: f64| force(Metres(x)).0, |x| stiffness(Metres(x)).0).unwrap());
+
+        let result = inv_cubic_solve_quantity(Metres(0.0), Metres(0.1), Metres(1e-12), 100, force, stiffness);
+        assert_eq!(result, Err(SolveError::NonBracketingInterval));
+    }
+}
@@ -0,0 +1,69 @@
+/*! The secant method, for when the root can't be bracketed */
+
+use num_traits::Float;
+
+use crate::SolveError;
+
+/** Find a root of func by the secant method, starting from x0 and x1. Unlike inv_cubic_solve, f(x0) and f(x1) don't
+  * need opposite signs, but nothing keeps the iterates near the starting points either. The method has converged once
+  * the step or the residual is below tol. If the secant is flat, i.e. f(x0) == f(x1) at some point, there is no next
+  * point and the error is DidNotConverge with the better of the two points.
+  */
+pub fn secant_solve<T, F>(x0: T, x1: T, tol: T, max_iters: usize, func: F) -> Result<T, SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> T
+{
+    let eval = |x: T| {
+        let f = func(x);
+        if f.is_finite() { Ok(f) } else { Err(SolveError::NonFiniteFunctionValue { x }) }
+    };
+
+    let (mut x0, mut x1) = (x0, x1);
+    let (mut f0, mut f1) = (eval(x0)?, eval(x1)?);
+
+    for _ in 0..max_iters {
+        if f1 == T::zero() {
+            return Ok(x1);
+        }
+        if f1 == f0 {
+            return Err(SolveError::DidNotConverge { best: if f0.abs() < f1.abs() { x0 } else { x1 } });
+        }
+
+        let dx = -f1*(x1 - x0)/(f1 - f0);
+        let x2 = x1 + dx;
+        let f2 = eval(x2)?;
+
+        // if the method has converged, return the new point
+        if dx.abs() < tol || f2.abs() < tol {
+            return Ok(x2);
+        }
+
+        x0 = x1; f0 = f1;
+        x1 = x2; f1 = f2;
+    }
+
+    Err(SolveError::DidNotConverge { best: x1 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inv_cubic_solve;
+
+    #[test]
+    fn secant_agrees_with_the_inverse_cubic() {
+        let func = |x: f64| x.powi(3) - 2.0;
+        // both guesses are above the root, so they don't bracket it
+        let root = secant_solve(1.5, 1.6, 1e-13, 100, func).unwrap();
+        let expected = inv_cubic_solve(1.0, 2.0, 1e-13, 100, func, |x| 3.0*x*x).unwrap();
+        assert!((root - expected).abs() < 1e-12, "{} vs {}", root, expected);
+        assert!((root - 2f64.cbrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn flat_secant_is_an_error() {
+        let result = secant_solve(-1.0, 1.0, 1e-13, 100, |x: f64| x*x - 4.0);
+        assert!(matches!(result, Err(SolveError::DidNotConverge { .. })), "{:?}", result);
+    }
+}
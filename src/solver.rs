@@ -0,0 +1,3446 @@
+/*! The inverse cubic solver: the Solver settings, the bracket state and the steps that shrink it, and the free
+  * functions built on them
+  */
+
+use core::cell::Cell;
+use num_traits::Float;
+
+use crate::{constant, safe_div, SolveError, BISECTION_FREQ, BRACKET_GROWTH, DEFAULT_MAX_EXPANSIONS, DEFAULT_MAX_ITERS, MONOTONE_MAX_FIRST_STEP, SUSPICIOUS_BRACKETS};
+use crate::convergence::Converged;
+use crate::interpolation::{two_point_cubic, two_point_cubic_inverse_at};
+use crate::predictor::{AcceptStep, CubicPredictor, Predictor};
+
+/** A snapshot of the solver state, passed to the per-iteration callback */
+#[derive(Debug, Clone, Copy)]
+pub struct IterationInfo<T = f64> {
+   /// iteration number, starting from 1
+   pub n_iters: usize,
+   /// lower end of the bracket
+   pub x0: T,
+   /// upper end of the bracket
+   pub x1: T,
+   /// min(|f(x0)|, |f(x1)|)
+   pub f_min: T,
+   /// max(|f(x0)|, |f(x1)|)
+   pub f_max: T,
+   /// width of the bracket
+   pub dx: T,
+   /// the kind of step that produced this bracket, None for the initial bracket
+   pub step: Option<StepKind>
+}
+
+impl<T: Float> IterationInfo<T> {
+   /** log10 of the bracket width, the number of correct digits so far. This is None once the bracket has collapsed
+     * to a single point, e.g. when a step lands exactly on an end, rather than -inf.
+     */
+   pub fn log10_width(&self) -> Option<T> {
+       if self.dx > T::zero() { Some(self.dx.log10()) } else { None }
+   }
+}
+
+/** A one line summary of info for logging, with every number in scientific notation to the given number of significant
+  * digits (at least 1), e.g. `format_iteration(&info, 4)` gives
+  * `  3 x0 = 1.250e-1 x1 = 1.500e-1 f_min = 2.441e-4 f_max = 3.906e-3 dx = 2.500e-2 step = Bisection`.
+  * The numbers are written so that they parse back with str::parse.
+  */
+#[cfg(feature = "std")]
+pub fn format_iteration<T: Float + core::fmt::LowerExp>(info: &IterationInfo<T>, digits: usize) -> String {
+    let p = digits.max(1) - 1;
+    let step = match info.step {
+        Some(step) => format!("{:?}", step),
+        None => String::from("none")
+    };
+    format!("{:3} x0 = {:.*e} x1 = {:.*e} f_min = {:.*e} f_max = {:.*e} dx = {:.*e} step = {}",
+            info.n_iters, p, info.x0, p, info.x1, p, info.f_min, p, info.f_max, p, info.dx, step)
+}
+
+/** Write history, e.g. from solve_with_history, to w as CSV with the header `iter,x0,x1,f_min,f_max,width,log10_width`
+  * and a row per state. The numbers are in scientific notation with as many digits as it takes to parse back exactly,
+  * and log10_width is left empty for a bracket that has collapsed to a point.
+  */
+#[cfg(feature = "std")]
+pub fn write_history_csv<T, W>(history: &[BracketState<T>], mut w: W) -> std::io::Result<()>
+where
+    T: Float + core::fmt::LowerExp,
+    W: std::io::Write
+{
+    writeln!(w, "iter,x0,x1,f_min,f_max,width,log10_width")?;
+    for state in history {
+        let info = state.info();
+        write!(w, "{},{:e},{:e},{:e},{:e},{:e},", info.n_iters, info.x0, info.x1, info.f_min, info.f_max, info.dx)?;
+        match info.log10_width() {
+            Some(log10_width) => writeln!(w, "{:e}", log10_width)?,
+            None => writeln!(w)?
+        }
+    }
+    Ok(())
+}
+
+/** Empirical estimates of the order of convergence from a sequence of errors, e.g. the distance from the best end
+  * of each bracket in solve_with_history to the root. Each run of three errors e_{n-1}, e_n, e_{n+1} gives
+  * `ln(e_{n+1}/e_n) / ln(e_n/e_{n-1})`, which tends to the order of the method near a simple root: 1 for linear
+  * convergence and more than 1 for the superlinear steps.
+  *
+  * An error that is the same as the one before it, e.g. because a step moved the other end of the bracket, is
+  * dropped before forming the runs, and runs containing a zero error give no estimate. The bracket widths can be used
+  * instead, but they only shrink superlinearly while both ends keep moving.
+  */
+pub fn convergence_order<T: Float>(errors: &[T]) -> impl Iterator<Item = T> + '_ {
+    errors.iter()
+          .enumerate()
+          .filter(move |&(i, e)| i == 0 || e.abs() != errors[i - 1].abs())
+          .map(|(_, e)| e.abs())
+          .scan((None, None), |last, e2| {
+              let (e0, e1) = *last;
+              *last = (e1, Some(e2));
+              Some((e0, e1, e2))
+          })
+          .filter_map(|(e0, e1, e2)| {
+              let (e0, e1) = (e0?, e1?);
+              if e0 == T::zero() || e1 == T::zero() || e2 == T::zero() {
+                  return None;
+              }
+              Some((e2/e1).ln()/(e1/e0).ln())
+          })
+}
+
+/** Counts of the work done by the solver */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SolveStats {
+   /// number of steps taken, i.e. the number of times the bracket was updated
+   pub iterations: usize,
+   /// number of calls to func
+   pub func_evals: usize,
+   /// number of calls to deriv
+   pub deriv_evals: usize,
+   /// number of calls to the second derivative, if one was given
+   pub deriv2_evals: usize,
+   /// whether the tolerance was met before the iteration cap
+   pub converged: bool,
+   /// the kind of step that produced the final bracket, None if no steps were taken
+   pub last_step: Option<StepKind>,
+   /// whether the derivatives contradicted func for too many steps in a row, see BracketState::derivative_suspicious
+   pub derivative_suspicious: bool
+}
+
+/** Why a solve that found a point stopped, see Solver::solve_outcome */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum TerminationReason {
+   /// the bracket became narrower than the tolerance
+   WidthTolerance,
+   /// the residual at the best end fell below the tolerance, however wide the bracket still was
+   ResidualTolerance,
+   /// the iteration cap was reached first
+   MaxIterations,
+   /// the ends of the bracket became adjacent floats before the tolerance was met
+   MachinePrecision,
+   /// func was exactly zero at the root
+   ExactRoot
+}
+
+/** The root from Solver::solve_outcome, along with why the solve stopped there and the work done */
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Outcome<T = f64> {
+   pub root: T,
+   pub reason: TerminationReason,
+   pub stats: SolveStats
+}
+
+/** The different kinds of step the solver can take to shrink the bracket */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StepKind {
+   /// the point predicted by inverse cubic interpolation, or by the predictor given to Solver::solve_with_predictor
+   InverseCubic,
+   /// the point predicted by inverse quadratic interpolation through the ends and the last point dropped from the bracket,
+   /// used instead of the inverse cubic when the derivatives aren't reliable
+   InverseQuadratic,
+   /// a Newton step from one end of the bracket
+   Newton,
+   /// a Halley step from one end of the bracket, using the second derivative
+   Halley,
+   /// the point where the secant through the ends of the bracket crosses zero
+   FalsePosition,
+   /// the midpoint of the bracket
+   Bisection
+}
+
+/** How much Solver::solve_logged writes about a solve */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Verbosity {
+   /// nothing at all
+   Silent,
+   /// one line at the end, with the root or the error, and the work done, along with a warning if the derivatives at
+   /// the ends of the initial bracket have opposite signs, or if they look wrong, see BracketState::derivative_suspicious
+   Summary,
+   /// a line for every iteration, from format_iteration, then the summary
+   Trace
+}
+
+/** How the width and residual tolerances combine into the default convergence criterion, see Solver::tol_combine */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TolCombine {
+   /// converged once both the width and the residual are within their tolerances
+   And,
+   /// converged as soon as either is
+   Or
+}
+
+/** When the solver falls back on bisection, see Solver::strategy */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Strategy {
+   /// bisect every bisection_freq steps, whenever the same end of the bracket moves twice in a row, and whenever a
+   /// window of bisection_freq steps doesn't shrink the bracket by guaranteed_shrink
+   Robust,
+   /// only bisect when the other steps demonstrably fail: the window of bisection_freq steps doesn't shrink the bracket
+   /// by guaranteed_shrink, or the same end moves twice in a row with the bracket shrinking by less than
+   /// guaranteed_shrink on the second step
+   EvalMiserly
+}
+
+/** A configurable front end to the solver, e.g.
+  * `Solver::new().tol(1e-12).max_iters(100).bisection_freq(5).solve(x0, x1, func, deriv)`.
+  * Any setting that isn't given keeps its default. The solver only holds settings, the function and its derivatives
+  * are given to each solve, so with the serde feature it can be saved and loaded to make a run reproducible.
+  *
+  * T is any num_traits::Float, so fixed width types beyond f32 and f64 work as well, with the default tolerance
+  * following their epsilon. Arbitrary precision floats can't be used: they own heap memory, so they aren't Copy and
+  * can't implement Float, and the solver passes T around by value throughout. For those, the dashu feature adds
+  * big::inv_cubic_solve_big, which works by reference on dashu's decimal DBig.
+  *
+  * The results are reproducible across platforms: given the same settings, bracket and values of func and deriv, every
+  * target takes the same steps to the same bits. Rust never contracts a*b + c into a fused multiply-add or reorders
+  * float arithmetic, and the steps only use +, -, *, /, comparisons and sqrt, which IEEE 754 rounds exactly, so no
+  * deterministic mode is needed. The exceptions are the math functions whose last bit depends on the library that
+  * implements them: cbrt for the central differences of inv_cubic_solve_no_deriv and check_derivatives, atan for
+  * solve_unbounded, and exp and ln for solve_log_scale, along with whatever func itself calls. Targets whose floats
+  * aren't IEEE 754 binary32 and binary64, such as x87 without SSE2, can differ as well.
+  */
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Solver<T = f64> {
+   abs_tol: T,
+   f_tol: Option<T>,
+   tol_combine: TolCombine,
+   rel_tol: T,
+   max_iters: usize,
+   bisection_freq: usize,
+   newton_fallback: bool,
+   cubic: bool,
+   accept_frac: T,
+   guaranteed_shrink: T,
+   check_derivatives: Option<T>,
+   suspicious_brackets: usize,
+   stagnation: Option<(T, usize)>,
+   rel_residual: Option<T>,
+   verbosity: Verbosity,
+   func_eval_budget: Option<usize>,
+   perturbation: Option<T>,
+   strategy: Strategy,
+   #[cfg(feature = "std")]
+   timeout: Option<std::time::Duration>,
+   polish: bool
+}
+
+impl<T: Float> Solver<T> {
+   /** A solver with an absolute tolerance of sqrt(epsilon), no relative tolerance, a cap of 1000 iterations,
+     * and a bisection step every 5 iterations
+     */
+   pub fn new() -> Self {
+       Solver {
+           abs_tol: T::epsilon().sqrt(),
+           f_tol: None,
+           tol_combine: TolCombine::Or,
+           rel_tol: T::zero(),
+           max_iters: DEFAULT_MAX_ITERS,
+           bisection_freq: BISECTION_FREQ,
+           newton_fallback: false,
+           cubic: true,
+           accept_frac: T::one(),
+           guaranteed_shrink: constant(0.5),
+           check_derivatives: None,
+           suspicious_brackets: SUSPICIOUS_BRACKETS,
+           stagnation: None,
+           rel_residual: None,
+           verbosity: Verbosity::Silent,
+           func_eval_budget: None,
+           perturbation: None,
+           strategy: Strategy::Robust,
+           #[cfg(feature = "std")]
+           timeout: None,
+           polish: false
+       }
+   }
+
+   /** Absolute tolerance, applied to both the bracket width and the residual */
+   pub fn tol(mut self, tol: T) -> Self {
+       self.abs_tol = tol;
+       self.f_tol = None;
+       self
+   }
+
+   /** Absolute tolerance on the bracket width alone, leaving the residual tolerance as it was */
+   pub fn x_tol(mut self, x_tol: T) -> Self {
+       self.f_tol = Some(self.f_tol.unwrap_or(self.abs_tol));
+       self.abs_tol = x_tol;
+       self
+   }
+
+   /** Absolute tolerance on the residual min(|f0|, |f1|) alone, for when func and x are on different scales or in
+     * different units, so one tolerance can't suit both
+     */
+   pub fn f_tol(mut self, f_tol: T) -> Self {
+       self.f_tol = Some(f_tol);
+       self
+   }
+
+   /** Whether the solve stops once the width or the residual is within its tolerance, the default of Or, or only once
+     * both are, And. With And, a tiny residual at an end no longer stops the solve while the bracket is still wide,
+     * nor a narrow bracket while the residual is large, e.g. next to a pole or a jump that isn't a root.
+     */
+   pub fn tol_combine(mut self, tol_combine: TolCombine) -> Self {
+       self.tol_combine = tol_combine;
+       self
+   }
+
+   /** Relative tolerance, applied to the bracket width only */
+   pub fn rel_tol(mut self, rel_tol: T) -> Self {
+       self.rel_tol = rel_tol;
+       self
+   }
+
+   /** Maximum number of iterations before giving up */
+   pub fn max_iters(mut self, max_iters: usize) -> Self {
+       self.max_iters = max_iters;
+       self
+   }
+
+   /** How often a plain bisection step is forced. Smooth functions can afford a higher value, since the interpolation
+     * steps converge faster. A value of usize::MAX (or 0) turns off the periodic bisection, the solver still bisects
+     * whenever the same end of the bracket is moved twice in a row.
+     */
+   pub fn bisection_freq(mut self, bisection_freq: usize) -> Self {
+       self.bisection_freq = bisection_freq;
+       self
+   }
+
+   /** Whether to take the interpolation steps at all, true by default. With false, the inverse cubic (or the predictor)
+     * and the inverse quadratic steps are skipped, leaving false position and bisection (and Newton, if
+     * newton_fallback is set), a slower but well understood baseline to compare against. The derivative is then
+     * not evaluated unless something else needs it.
+     */
+   pub fn cubic(mut self, cubic: bool) -> Self {
+       self.cubic = cubic;
+       self
+   }
+
+   /** After converging, take one more Newton step from the best end of the bracket, or a secant step across it if the
+     * derivative there isn't known, and return that point instead if its residual is strictly smaller. Once the
+     * bracket is within a few floats of the root, this can get the last bit or two of accuracy, for one evaluation of
+     * func. Off by default.
+     */
+   pub fn polish(mut self, polish: bool) -> Self {
+       self.polish = polish;
+       self
+   }
+
+   /** When the cubic prediction is rejected, try a Newton step before falling back to false position */
+   pub fn newton_fallback(mut self, newton_fallback: bool) -> Self {
+       self.newton_fallback = newton_fallback;
+       self
+   }
+
+   /** Damping for the cubic steps: a cubic step that leaves a bracket wider than accept_frac times the old width
+     * barely helped, so the next step is a bisection instead of another prediction. The new point is still used, as
+     * it has already been paid for. The default of 1 accepts every cubic step.
+     */
+   pub fn accept_frac(mut self, accept_frac: T) -> Self {
+       self.accept_frac = accept_frac;
+       self
+   }
+
+   /** The worst case rate of convergence: every bisection_freq steps the bracket must shrink by at least this factor,
+     * otherwise bisection steps are forced until it has. This bounds the number of iterations by
+     * bisection_freq*log(initial width/tol)/log(1/guaranteed_shrink), plus the forced steps. The default of 0.5 is
+     * already met by the periodic bisection, a smaller value demands more of the interpolation steps.
+     */
+   pub fn guaranteed_shrink(mut self, guaranteed_shrink: T) -> Self {
+       self.guaranteed_shrink = guaranteed_shrink;
+       self
+   }
+
+   /** Check every derivative the solver uses against a central difference estimate, failing with DerivativeMismatch
+     * if they differ by more than the given relative tolerance. This costs two extra function evaluations per
+     * derivative, so it's meant for tracking down a wrong deriv. Near a stationary point the estimate is mostly
+     * rounding error, so the tolerance shouldn't be too tight. None, the default, turns the check off.
+     */
+   pub fn check_derivatives(mut self, rel_tol: Option<T>) -> Self {
+       self.check_derivatives = rel_tol;
+       self
+   }
+
+   /** Flag the derivative as suspicious, see BracketState::derivative_suspicious, once the derivatives at both ends
+     * have contradicted the secant for more than k brackets in a row. The default is 3.
+     */
+   pub fn suspicious_brackets(mut self, k: usize) -> Self {
+       self.suspicious_brackets = k;
+       self
+   }
+
+   /** Stop with Stagnated once the best point has moved by less than tol for k iterations in a row, e.g. for a noisy
+     * or tabulated function whose residual never drops below the tolerance. The best point also stays put while the
+     * other end of the bracket moves, so k should be more than a couple of iterations. Off by default.
+     */
+   pub fn stagnation(mut self, tol: T, k: usize) -> Self {
+       self.stagnation = Some((tol, k));
+       self
+   }
+
+   /** Measure the residual relative to the function values at the ends of the initial bracket instead of with the
+     * absolute tolerance: the solve stops once min(|f0|, |f1|) is within rel_residual*max(|f(x0)|, |f(x1)|) of the
+     * initial x0 and x1. Scaling func by any constant then leaves the result unchanged, where an absolute residual
+     * tolerance is met straight away by a tiny func and never by a huge one. The width tolerances still apply.
+     * As it needs the initial bracket, this is applied by the solve methods rather than by the Converged impl, and
+     * a criterion given to solve_until is used alongside it, combined as set by tol_combine. Off by default.
+     */
+   pub fn rel_residual(mut self, rel_residual: T) -> Self {
+       self.rel_residual = Some(rel_residual);
+       self
+   }
+
+   /** Stop with BudgetExhausted rather than take a step that would evaluate func more than budget times in all, for when
+     * each evaluation is expensive. The evaluations for the initial bracket are always made, and the derivative
+     * evaluations aren't counted. The extra evaluations made by check_derivatives are, so the budget is never
+     * exceeded once the solve is under way. Off by default.
+     */
+   pub fn func_eval_budget(mut self, budget: usize) -> Self {
+       self.func_eval_budget = Some(budget);
+       self
+   }
+
+   /** Move a predicted point that lands on an end of the bracket, to within rounding, inwards by perturbation times
+     * the width. Such a point has already been evaluated, so the step would be wasted, and as the bracket is unchanged
+     * the same point tends to be predicted again, e.g. for a symmetric function or a bracket only a few floats wide.
+     * Every point evaluated so far is an end of the bracket or outside it, so the ends are the only repeats there are.
+     * A small value such as 0.01 is enough. Off by default.
+     */
+   pub fn perturbation(mut self, perturbation: T) -> Self {
+       self.perturbation = Some(perturbation);
+       self
+   }
+
+   /** When to fall back on bisection, Robust by default. EvalMiserly is for functions so expensive that every
+     * evaluation counts: on a smooth function the interpolation steps converge without help, and the bisections the
+     * robust schedule forces anyway are mostly wasted evaluations. The price is the worst case, where each window of
+     * bisection_freq steps is only guaranteed to shrink the bracket by guaranteed_shrink, instead of halving it
+     * every bisection_freq steps whatever the function, so on a badly behaved function it can take more evaluations.
+     */
+   pub fn strategy(mut self, strategy: Strategy) -> Self {
+       self.strategy = strategy;
+       self
+   }
+
+   /** Stop with TimedOut once a solve has taken longer than timeout, for when the cost of an iteration varies too much
+     * for max_iters or func_eval_budget to bound the time. The clock is read once per iteration, only if a timeout is
+     * set, which costs far less than an evaluation of all but the cheapest functions. An iteration that has started
+     * isn't interrupted, so the solve overruns by up to one step. The time includes the evaluations at the ends of the
+     * initial bracket. Off by default.
+     */
+   #[cfg(feature = "std")]
+   pub fn timeout(mut self, timeout: std::time::Duration) -> Self {
+       self.timeout = Some(timeout);
+       self
+   }
+
+   /** How much solve_logged writes, Silent by default */
+   pub fn verbosity(mut self, verbosity: Verbosity) -> Self {
+       self.verbosity = verbosity;
+       self
+   }
+
+   /** Find a root of func in the bracket [x0, x1] */
+   pub fn solve<F, D>(&self, x0: T, x1: T, func: F, deriv: D) -> Result<T, SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T
+   {
+       self.solve_with_callback(x0, x1, func, deriv, |_| {})
+   }
+
+   /** Step through the solution of func on the bracket [x0, x1] one iteration at a time, see SolveIter */
+   pub fn iter<F, D>(&self, x0: T, x1: T, func: F, deriv: D) -> Result<SolveIter<T, F, D>, SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T
+   {
+       SolveIter::new(*self, x0, x1, func, deriv)
+   }
+
+   /** Same as solve, but stops when criterion says so instead of using the tolerances of this solver, e.g.
+     * `Solver::new().solve_until(x0, x1, func, deriv, &ResidualTolerance(1e-12))`. An exact root always stops the solver.
+     */
+   pub fn solve_until<F, D, Cv>(&self, x0: T, x1: T, func: F, deriv: D, criterion: &Cv) -> Result<T, SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T,
+       Cv: Converged<T>
+   {
+       let solution = solve_core(self, criterion, Start::Bracket(x0, x1), func, deriv, None::<fn(T) -> T>, CubicPredictor, |_| true)?.into_result()?;
+       Ok(solution.root)
+   }
+
+   /** Same as solve, but the interpolation steps are predicted by predictor instead of the inverse cubic. The
+     * bisection and false position steps that keep the solve robust are unchanged, so even a poor predictor
+     * converges, just more slowly. With cubic(false) only the predictor's accept_step is used.
+     */
+   pub fn solve_with_predictor<F, D, P>(&self, x0: T, x1: T, func: F, deriv: D, predictor: P) -> Result<T, SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T,
+       P: Predictor<T>
+   {
+       let solution = solve_core(self, self, Start::Bracket(x0, x1), func, deriv, None::<fn(T) -> T>, predictor, |_| true)?.into_result()?;
+       Ok(solution.root)
+   }
+
+   /** Same as solve, but accept_step is called with every point about to be evaluated, other than a bisection, along
+     * with the current state. Returning Some(x) evaluates x instead, which may be the point itself, and None bisects
+     * the bracket, e.g. to keep the solver out of a region where func is expensive or unreliable. A point outside
+     * the bracket bisects too, so the solve still converges whatever the hook does.
+     */
+   pub fn solve_with_hook<F, D, H>(&self, x0: T, x1: T, func: F, deriv: D, accept_step: H) -> Result<T, SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T,
+       H: Fn(T, &BracketState<T>) -> Option<T>
+   {
+       self.solve_with_predictor(x0, x1, func, deriv, AcceptStep { predictor: CubicPredictor, hook: accept_step })
+   }
+
+   /** The state for the bracket [x0, x1] before any steps are taken, to be advanced with solve_step */
+   pub fn initial_state<F, D>(&self, x0: T, x1: T, func: &F, deriv: &D) -> Result<BracketState<T>, SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T
+   {
+       BracketState::new(x0, x1, func, deriv, self.needs_deriv(), self.check_derivatives)
+   }
+
+   /** Same as solve, but starts from a single guess and searches for a bracket around it first with find_bracket */
+   pub fn solve_from<F, D>(&self, guess: T, func: F, deriv: D) -> Result<T, SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T
+   {
+       let step = guess.abs().max(T::one())*constant(0.01);
+       match find_bracket(&func, guess, step, DEFAULT_MAX_EXPANSIONS) {
+           Some((x0, x1)) => self.solve(x0, x1, func, deriv),
+           None => Err(SolveError::NonBracketingInterval)
+       }
+   }
+
+   /** Same as solve_from, but for a function known to be monotone, so that the sign of f/f' at the guess says which
+     * way the root is. The bracket is only searched for in that direction, starting with the Newton step, at most
+     * 100*max(|guess|, 1) long, and growing it each time no sign change is found. A step that lands where func isn't
+     * finite, e.g. where exp overflows, is halved and tried again instead, which counts towards the same limit of 50
+     * expansions. If the derivative at the guess is zero or not finite, this falls back to solve_from.
+     */
+   pub fn solve_monotone<F, D>(&self, guess: T, func: F, deriv: D) -> Result<T, SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T
+   {
+       let growth: T = constant(BRACKET_GROWTH);
+
+       let f = func(guess);
+       let df = deriv(guess);
+       if !f.is_finite() {
+           return Err(SolveError::NonFiniteFunctionValue { x: guess });
+       }
+       if f == T::zero() {
+           return Ok(guess);
+       }
+       if df == T::zero() || !df.is_finite() {
+           return self.solve_from(guess, func, deriv);
+       }
+
+       // the near end moves out along with the far end, so the final bracket is no wider than the last step
+       let max_step = guess.abs().max(T::one())*constant(MONOTONE_MAX_FIRST_STEP);
+       let mut near = guess;
+       let mut step = (-f/df).max(-max_step).min(max_step);
+       for _ in 0..DEFAULT_MAX_EXPANSIONS {
+           let far = near + step;
+           let f_far = func(far);
+           if !f_far.is_finite() {
+               step = step/constant(2.0);
+               continue;
+           }
+           if f_far == T::zero() || opposite_signs(f, f_far) {
+               return self.solve(near, far, func, deriv);
+           }
+           near = far;
+           step = step*growth;
+       }
+       Err(SolveError::NonBracketingInterval)
+   }
+
+   /** Same as solve, but calls on_iter with the current state at the start of every iteration */
+   pub fn solve_with_callback<F, D, C>(&self, x0: T, x1: T, func: F, deriv: D, mut on_iter: C) -> Result<T, SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T,
+       C: FnMut(&IterationInfo<T>)
+   {
+       let solution = solve_core(self, self, Start::Bracket(x0, x1), func, deriv, None::<fn(T) -> T>, CubicPredictor, |state| { on_iter(&state.info()); true })?.into_result()?;
+       Ok(solution.root)
+   }
+
+   /** Same as solve, but should_continue is called once per iteration, and the solve stops with Cancelled as soon as
+     * it returns false, e.g. to cancel a long solve from another thread with
+     * `solver.solve_cancellable(x0, x1, func, deriv, || !cancel.load(Ordering::Relaxed))` for an AtomicBool cancel.
+     */
+   pub fn solve_cancellable<F, D, S>(&self, x0: T, x1: T, func: F, deriv: D, should_continue: S) -> Result<T, SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T,
+       S: Fn() -> bool
+   {
+       let solution = solve_core(self, self, Start::Bracket(x0, x1), func, deriv, None::<fn(T) -> T>, CubicPredictor, |_| should_continue())?.into_result()?;
+       Ok(solution.root)
+   }
+
+   /** Same as solve, but also returns every state the solver went through, starting with the initial bracket, so the
+     * last state has n_iters equal to the length of the history. This allocates, so it's meant for debugging.
+     */
+   #[cfg(feature = "std")]
+   pub fn solve_with_history<F, D>(&self, x0: T, x1: T, func: F, deriv: D) -> Result<(T, Vec<BracketState<T>>), SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T
+   {
+       let mut history = Vec::new();
+       let solution = solve_core(self, self, Start::Bracket(x0, x1), func, deriv, None::<fn(T) -> T>, CubicPredictor, |state| { history.push(*state); true })?.into_result()?;
+       Ok((solution.root, history))
+   }
+
+   /** Same as solve_with_history, but the history is recorded into scratch, which is cleared first, rather than a
+     * new Vec. Reusing the same scratch across many solves only allocates when a solve takes more iterations than
+     * any before it.
+     */
+   #[cfg(feature = "std")]
+   pub fn solve_with_scratch<F, D>(&self, x0: T, x1: T, func: F, deriv: D, scratch: &mut SolveScratch<T>) -> Result<T, SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T
+   {
+       scratch.history.clear();
+       let history = &mut scratch.history;
+       let solution = solve_core(self, self, Start::Bracket(x0, x1), func, deriv, None::<fn(T) -> T>, CubicPredictor, |state| { history.push(*state); true })?.into_result()?;
+       Ok(solution.root)
+   }
+
+   /** Same as solve, but writes about the solve to out as selected with verbosity, e.g. `&mut std::io::stderr()` or a
+     * `Vec<u8>` to capture it. The root is written in full, the other numbers to 6 significant digits. The log is only
+     * diagnostics, so an error writing to out is ignored rather than failing the solve.
+     */
+   #[cfg(feature = "std")]
+   pub fn solve_logged<F, D>(&self, x0: T, x1: T, func: F, deriv: D, out: &mut dyn std::io::Write) -> Result<T, SolveError<T>>
+   where
+       T: core::fmt::LowerExp + core::fmt::Display,
+       F: Fn(T) -> T,
+       D: Fn(T) -> T
+   {
+       let trace = self.verbosity == Verbosity::Trace;
+       let silent = self.verbosity == Verbosity::Silent;
+       let mut warned_suspicious = false;
+       let solution = solve_core(self, self, Start::Bracket(x0, x1), func, deriv, None::<fn(T) -> T>, CubicPredictor, |state| {
+           if !silent && state.n_iters == 1 && state.derivative_signs_differ() {
+               let _ = writeln!(out, "warning: the derivatives at the ends of the bracket have opposite signs, it may contain a turning point");
+           }
+           if !silent && !warned_suspicious && state.derivative_suspicious() {
+               warned_suspicious = true;
+               let _ = writeln!(out, "warning: the derivatives contradicted the secant for {} brackets in a row, deriv may not match func",
+                                state.contradicting_derivatives());
+           }
+           if trace {
+               let _ = writeln!(out, "{}", format_iteration(&state.info(), 6));
+           }
+           true
+       });
+       let solution = solution.and_then(Solution::into_result);
+       let result = solution.as_ref().map(|solution| solution.root).map_err(|&e| e);
+
+       if !silent {
+           let _ = match (&result, &solution) {
+               (Ok(root), Ok(solution)) => writeln!(out, "root = {:e} f_min = {:.5e} iterations = {} func_evals = {} deriv_evals = {}",
+                                                    root, solution.state.f_min(), solution.stats.iterations,
+                                                    solution.stats.func_evals, solution.stats.deriv_evals),
+               (Err(e), _) => writeln!(out, "solver failed: {}", e),
+               _ => Ok(())
+           };
+       }
+       result
+   }
+
+   /** Same as solve, but also reports how much work was done. Hitting the iteration cap is not an error
+     * here, instead the best point is returned with converged set to false in the stats.
+     */
+   pub fn solve_with_stats<F, D>(&self, x0: T, x1: T, func: F, deriv: D) -> Result<(T, SolveStats), SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T
+   {
+       let solution = solve_core(self, self, Start::Bracket(x0, x1), func, deriv, None::<fn(T) -> T>, CubicPredictor, |_| true)?;
+       Ok((solution.root, solution.stats))
+   }
+
+   /** Same as solve_with_stats, but also says why the solve stopped. Running out of iterations or of floating point
+     * precision aren't errors here, the best point found is returned with MaxIterations or MachinePrecision as the
+     * reason. Everything else that can go wrong, e.g. a bracket without a sign change, is still an error.
+     */
+   pub fn solve_outcome<F, D>(&self, x0: T, x1: T, func: F, deriv: D) -> Result<Outcome<T>, SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T
+   {
+       // the state and the evaluations so far, for when machine precision ends the solve with an error
+       let last = Cell::new(None);
+       let (func_evals, deriv_evals) = (Cell::new(0), Cell::new(0));
+       let counted_func = |x| { func_evals.set(func_evals.get() + 1); func(x) };
+       let counted_deriv = |x| { deriv_evals.set(deriv_evals.get() + 1); deriv(x) };
+
+       let result = solve_core(self, self, Start::Bracket(x0, x1), counted_func, counted_deriv, None::<fn(T) -> T>, CubicPredictor,
+                               |state| { last.set(Some(*state)); true });
+       let solution = match (result, last.get()) {
+           (Err(SolveError::MachinePrecisionReached { .. }), Some(state)) => {
+               let stats = SolveStats {
+                   iterations: state.n_iters - 1,
+                   func_evals: func_evals.get(),
+                   deriv_evals: deriv_evals.get(),
+                   deriv2_evals: 0,
+                   converged: false,
+                   last_step: state.step,
+                   derivative_suspicious: state.derivative_suspicious()
+               };
+               return Ok(Outcome { root: state.root(), reason: TerminationReason::MachinePrecision, stats });
+           },
+           (result, _) => result?
+       };
+
+       let BracketState { x0, x1, f0, f1, .. } = solution.state;
+       let reason = if !solution.stats.converged {
+           TerminationReason::MaxIterations
+       } else if solution.state.f_min() == T::zero() {
+           TerminationReason::ExactRoot
+       } else if WidthCriterion(self).is_converged(x0, x1, f0, f1) {
+           TerminationReason::WidthTolerance
+       } else {
+           TerminationReason::ResidualTolerance
+       };
+       Ok(Outcome { root: solution.root, reason, stats: solution.stats })
+   }
+
+   /** Same as solve, but func_deriv returns the function value and the derivative together, for when they share
+     * expensive work, e.g. both need x.exp(), or both come out of a forward pass of automatic differentiation.
+     * func_deriv is called once per point.
+     */
+   pub fn solve_combined<G>(&self, x0: T, x1: T, func_deriv: G) -> Result<T, SolveError<T>>
+   where
+       G: Fn(T) -> (T, T)
+   {
+       // the solver asks for f and f' at a point separately, so the last two points are remembered. That is enough
+       // for the initial bracket, where f is evaluated at both ends before either derivative.
+       let cache = Cell::new([None::<(T, T, T)>; 2]);
+       let eval = |x: T| {
+           let last = cache.get();
+           if let Some(&(_, f, df)) = last.iter().flatten().find(|e| e.0 == x) {
+               return (f, df);
+           }
+           let (f, df) = func_deriv(x);
+           cache.set([Some((x, f, df)), last[0]]);
+           (f, df)
+       };
+       self.solve(x0, x1, |x| eval(x).0, |x| eval(x).1)
+   }
+
+   /** Same as solve, but with the second derivative of func, so that Halley steps can be taken. These converge
+     * cubically near a simple root, and fall back to the usual steps whenever they leave the bracket.
+     */
+   pub fn solve_halley<F, D, D2>(&self, x0: T, x1: T, func: F, deriv: D, deriv2: D2) -> Result<T, SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T,
+       D2: Fn(T) -> T
+   {
+       let solution = solve_core(self, self, Start::Bracket(x0, x1), func, deriv, Some(deriv2), CubicPredictor, |_| true)?.into_result()?;
+       Ok(solution.root)
+   }
+
+   /** Same as solve, but also returns deriv(root), e.g. to judge how well conditioned the root is, as a small
+     * derivative means a small residual still leaves the root uncertain. This is the exact derivative, not an
+     * interpolated one. It has usually been evaluated already at the best end of the bracket, otherwise deriv is
+     * called once more at the root.
+     */
+   pub fn solve_with_derivative<F, D>(&self, x0: T, x1: T, func: F, deriv: D) -> Result<(T, T), SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T
+   {
+       let solution = solve_core(self, self, Start::Bracket(x0, x1), &func, &deriv, None::<fn(T) -> T>, CubicPredictor, |_| true)?.into_result()?;
+       let df = solution.state.df_best();
+       if solution.root == solution.state.x_best() && !df.is_nan() {
+           Ok((solution.root, df))
+       } else {
+           Ok((solution.root, deriv(solution.root)))
+       }
+   }
+
+   /** Same as solve, but the ends of the bracket are given as (x, f(x), f'(x)), e.g. from an earlier search, so they
+     * aren't evaluated again. The values are only checked for bracketing a root, not against func and deriv, and the
+     * derivatives are used as given even with check_derivatives on.
+     */
+   pub fn solve_precomputed<F, D>(&self, end0: (T, T, T), end1: (T, T, T), func: F, deriv: D) -> Result<T, SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T
+   {
+       let ((x0, f0, df0), (x1, f1, df1)) = if end1.0 < end0.0 { (end1, end0) } else { (end0, end1) };
+       BracketState::check_ends(x0, x1, f0, f1)?;
+       let state = BracketState::from_ends(x0, x1, f0, f1, df0, df1);
+
+       let solution = solve_core(self, self, Start::State(state), func, deriv, None::<fn(T) -> T>, CubicPredictor, |_| true)?.into_result()?;
+       Ok(solution.root)
+   }
+
+   /** Same as solve, but returns the final bracket [x0, x1], which is guaranteed to contain a root of func.
+     * If convergence was due to the residual rather than the width, the bracket may be wider than the tolerance.
+     */
+   pub fn solve_bracket<F, D>(&self, x0: T, x1: T, func: F, deriv: D) -> Result<(T, T), SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T
+   {
+       let solution = solve_core(self, self, Start::Bracket(x0, x1), func, deriv, None::<fn(T) -> T>, CubicPredictor, |_| true)?.into_result()?;
+       Ok((solution.state.x0, solution.state.x1))
+   }
+
+   /** Same as solve_bracket, but starts from the final bracket of an earlier solve, e.g. of the same equation for a
+     * slightly different parameter in a sweep, widened by widen on either side. widen should be about how far the
+     * root is expected to have moved. If the widened bracket no longer contains a root, find_bracket searches
+     * outwards from it, and NonBracketingInterval is returned if that fails too. The result is the bracket to warm
+     * start the next solve of the sweep, (root - widen, root + widen) clipped to the bracket this solve started from.
+     * This is centred on the root, unlike the final bracket, which often has one end at the root and the other well
+     * away from it.
+     */
+   pub fn solve_warm<F, D>(&self, bracket: (T, T), widen: T, func: F, deriv: D) -> Result<(T, T), SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T
+   {
+       let (x0, x1) = (bracket.0.min(bracket.1) - widen.abs(), bracket.0.max(bracket.1) + widen.abs());
+       let state = match self.initial_state(x0, x1, &func, &deriv) {
+           Err(SolveError::NonBracketingInterval) => match find_bracket(&func, naive_bisection(x0, x1), (x1 - x0)/constant(2.0), DEFAULT_MAX_EXPANSIONS) {
+               Some((x0, x1)) => self.initial_state(x0, x1, &func, &deriv)?,
+               None => return Err(SolveError::NonBracketingInterval)
+           },
+           result => result?
+       };
+       let (lo, hi) = (state.x0, state.x1);
+
+       let solution = solve_core(self, self, Start::State(state), func, deriv, None::<fn(T) -> T>, CubicPredictor, |_| true)?.into_result()?;
+       Ok(((solution.root - widen.abs()).max(lo), (solution.root + widen.abs()).min(hi)))
+   }
+
+   /** Same as solve_bracket, but the result is a rigorous enclosure [x0, x1] of a root of func, for verified
+     * computation. The ends of the bracket are always points where func was evaluated and had opposite signs (or was
+     * zero, in which case both ends are that point), and they are never the result of arithmetic on the enclosure,
+     * so no directed rounding is needed: if func is continuous, it has a root in the closed interval. The
+     * guarantee is only as good as the signs of the computed func, so near the root func should be evaluated
+     * accurately enough to get the sign right, e.g. with interval arithmetic returning a value of the certain sign.
+     *
+     * Unlike solve_bracket, the residual tolerances are ignored, so the width of the enclosure is below
+     * tol + rel_tol*|root| unless a root was found exactly. A tolerance too small for the floating point type gives
+     * the narrowest enclosure there is, with adjacent ends, rather than MachinePrecisionReached.
+     */
+   pub fn solve_verified<F, D>(&self, x0: T, x1: T, func: F, deriv: D) -> Result<(T, T), SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T
+   {
+       let cfg = Solver { rel_residual: None, ..*self };
+       let last = Cell::new(None);
+       let result = solve_core(&cfg, &WidthCriterion(&cfg), Start::Bracket(x0, x1), func, deriv, None::<fn(T) -> T>, CubicPredictor,
+                               |state| { last.set(Some(*state)); true });
+       let state = match result.and_then(Solution::into_result) {
+           Ok(solution) => solution.state,
+           Err(SolveError::MachinePrecisionReached { best }) => last.get().ok_or(SolveError::MachinePrecisionReached { best })?,
+           Err(e) => return Err(e)
+       };
+       if state.f_min() == T::zero() {
+           Ok((state.x_best(), state.x_best()))
+       } else {
+           Ok((state.x0, state.x1))
+       }
+   }
+
+   /** Same as solve, for a root between positive x0 and x1, found in u = ln(x) by solving g(u) = func(exp(u)) with
+     * g'(u) = deriv(exp(u))*exp(u), for roots that could be anywhere over many orders of magnitude. The tolerance
+     * on the width applies to u, so it is effectively relative to x, e.g. a root near 1e-9 is found to about
+     * tol*1e-9 without an absolute tolerance that small. Unless set separately, with f_tol or rel_residual, the
+     * residual doesn't end the solve, since tol is usually far too large for func near a tiny root: with a tol of 1e-12,
+     * x - 3e-9 is within it anywhere from 2.999e-9 to 3.001e-9. A bracket whose ends aren't both positive gives
+     * NonBracketingInterval.
+     */
+   pub fn solve_log_scale<F, D>(&self, x0: T, x1: T, func: F, deriv: D) -> Result<T, SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T
+   {
+       if !(x0 > T::zero() && x1 > T::zero()) {
+           return Err(SolveError::NonBracketingInterval);
+       }
+       let g = |u: T| func(u.exp());
+       let dg = |u: T| deriv(u.exp())*u.exp();
+       // tol is for the width in u alone, unless the residual has a tolerance of its own
+       let cfg = match (self.f_tol, self.tol_combine) {
+           (None, TolCombine::Or) => self.f_tol(T::zero()),
+           _ => *self
+       };
+       let root = cfg.solve(x0.ln(), x1.ln(), g, dg).map_err(|e| match e.map_points(T::exp) {
+           SolveError::DerivativeMismatch { x, analytic, numeric } => SolveError::DerivativeMismatch { x, analytic: analytic/x, numeric: numeric/x },
+           e => e
+       })?;
+       Ok(root.exp())
+   }
+
+   /** Same as solve, but either end of the bracket, or both, may be infinite, e.g. `solve_unbounded(1.0, f64::INFINITY,
+     * func, deriv)` for a root somewhere above 1. The solver works on a finite interval of t instead, with
+     * x = a + t/(1 - t) for [a, inf], x = b + t/(1 + t) for [-inf, b] and x = t/(1 - t^2) for [-inf, inf], while
+     * func is replaced by atan(func), which keeps the roots and is still finite where func overflows. func is evaluated
+     * at the infinite ends, so func(inf) must be finite or infinite with the right sign, not NaN. The tolerances still
+     * apply to x and func, but the points the solver can reach are only about x^2*epsilon apart for large x, so for a
+     * root far out a finite bracket from find_bracket is more accurate. With both ends finite, this is just solve.
+     */
+   pub fn solve_unbounded<F, D>(&self, x0: T, x1: T, func: F, deriv: D) -> Result<T, SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T
+   {
+       let (lo, hi) = (x0.min(x1), x0.max(x1));
+       let transform = match (lo.is_infinite(), hi.is_infinite()) {
+           _ if lo == hi => return self.solve(x0, x1, func, deriv),
+           (false, true) => Unbounded::Above(lo),
+           (true, false) => Unbounded::Below(hi),
+           (true, true) => Unbounded::Both,
+           (false, false) => return self.solve(x0, x1, func, deriv)
+       };
+       let (t0, t1) = transform.t_bracket();
+
+       // with u = atan(f), du/dt = f'(x)*dx/dt/(1 + f^2)
+       let g = |t: T| func(transform.x(t)).atan();
+       let dg = |t: T| {
+           let x = transform.x(t);
+           let f = func(x);
+           deriv(x)*transform.dx_dt(t)/(T::one() + f*f)
+       };
+       let criterion = UnboundedCriterion { cfg: self, transform };
+       let solution = solve_core(self, &criterion, Start::Bracket(t0, t1), g, dg, None::<fn(T) -> T>, CubicPredictor, |_| true)
+           .and_then(Solution::into_result)
+           .map_err(|e| e.map_points(|t| transform.x(t)))?;
+       Ok(transform.x(solution.root))
+   }
+}
+
+impl<T: Float> Solver<T> {
+   /** Whether any of the selected steps make use of the derivative */
+   pub(crate) fn needs_deriv(&self) -> bool {
+       self.cubic || self.newton_fallback
+   }
+}
+
+/** The default criterion: the width is within abs_tol + rel_tol*|x_best|, or (and, with TolCombine::And) the
+  * residual is within f_tol, which is abs_tol unless set separately. With rel_residual set, only the width is checked
+  * here, and the solve methods combine it with the relative residual.
+  */
+impl<T: Float> Converged<T> for Solver<T> {
+   fn is_converged(&self, x0: T, x1: T, f0: T, f1: T) -> bool {
+       let width_met = WidthCriterion(self).is_converged(x0, x1, f0, f1);
+       let residual_met = f0.abs().min(f1.abs()) < self.f_tol.unwrap_or(self.abs_tol);
+       match (self.tol_combine, self.rel_residual) {
+           (TolCombine::Or, None) => width_met || residual_met,
+           (TolCombine::And, None) => width_met && residual_met,
+           (_, Some(_)) => width_met
+       }
+   }
+}
+
+/** The width part of the Solver criterion alone, for Solver::solve_verified and Solver::solve_outcome */
+struct WidthCriterion<'a, T>(&'a Solver<T>);
+
+impl<T: Float> Converged<T> for WidthCriterion<'_, T> {
+   fn is_converged(&self, x0: T, x1: T, f0: T, f1: T) -> bool {
+       let x_best = if f0.abs() < f1.abs() { x0 } else { x1 };
+       (x1 - x0).abs() < self.0.abs_tol + self.0.rel_tol*x_best.abs()
+   }
+}
+
+impl<T: Float> Default for Solver<T> {
+   fn default() -> Self {
+       Solver::new()
+   }
+}
+
+/** The change of variables for Solver::solve_unbounded, by which ends of the bracket are infinite */
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Unbounded<T> {
+   /// [a, inf], from t in [0, 1]
+   Above(T),
+   /// [-inf, b], from t in [-1, 0]
+   Below(T),
+   /// [-inf, inf], from t in [-1, 1]
+   Both
+}
+
+impl<T: Float> Unbounded<T> {
+   fn t_bracket(&self) -> (T, T) {
+       match self {
+           Unbounded::Above(_) => (T::zero(), T::one()),
+           Unbounded::Below(_) => (-T::one(), T::zero()),
+           Unbounded::Both => (-T::one(), T::one())
+       }
+   }
+
+   /** The point x for t, which is infinite at the infinite ends of the bracket since t/0 is */
+   fn x(&self, t: T) -> T {
+       match *self {
+           Unbounded::Above(a) => a + t/(T::one() - t),
+           Unbounded::Below(b) => b + t/(T::one() + t),
+           Unbounded::Both => t/(T::one() - t*t)
+       }
+   }
+
+   fn dx_dt(&self, t: T) -> T {
+       match *self {
+           Unbounded::Above(_) => T::one()/((T::one() - t)*(T::one() - t)),
+           Unbounded::Below(_) => T::one()/((T::one() + t)*(T::one() + t)),
+           Unbounded::Both => (T::one() + t*t)/((T::one() - t*t)*(T::one() - t*t))
+       }
+   }
+}
+
+/** The criterion of a solver, applied to x and func rather than to t and atan(func) */
+struct UnboundedCriterion<'a, T> {
+   cfg: &'a Solver<T>,
+   transform: Unbounded<T>
+}
+
+impl<'a, T: Float> Converged<T> for UnboundedCriterion<'a, T> {
+   fn is_converged(&self, t0: T, t1: T, g0: T, g1: T) -> bool {
+       self.cfg.is_converged(self.transform.x(t0), self.transform.x(t1), g0.tan(), g1.tan())
+   }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CoordinateChangeFlag {
+   First,
+   Second,
+   Reset
+}
+
+/** The next point to evaluate, chosen from the current bracket alone, and the kind of step that chose it */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Proposal<T> {
+   pub(crate) x: T,
+   step: StepKind,
+   // whether this is false position with the Illinois modification
+   illinois: bool
+}
+
+/** Evaluate func at x_new, which must give a finite value */
+#[inline]
+pub(crate) fn evaluate<T: Float, F: Fn(T) -> T>(x_new: T, func: &F) -> Result<T, SolveError<T>> {
+   let f_new = func(x_new);
+   if f_new.is_finite() { Ok(f_new) } else { Err(SolveError::NonFiniteFunctionValue { x: x_new }) }
+}
+
+/** Given f_new = f(x_new) at a point inside the bracket, keep whichever half still brackets the root */
+fn split_bracket<T: Float> (x0: T, x1: T, f0: T, f1: T, x_new: T, f_new: T) -> Result<(T, T), SolveError<T>>{
+   // The invariant: f0 and f1 have opposite signs, or one of them is zero. It holds for the initial bracket, which is
+   // checked in BracketState::new, and each split keeps it, as the ends are never evaluated again, so even a function
+   // that answers differently from one call to the next can't break it. Only a state whose public fields were changed
+   // by hand can, which is caught here in debug builds, while release builds return NonBracketingInterval below.
+   debug_assert!(f0 == T::zero() || f1 == T::zero() || opposite_signs(f0, f1), "bracket invariant violated: f0 = {:?}, f1 = {:?}", f0.to_f64(), f1.to_f64());
+
+   // an exact root is bracketed by both halves. The tie always goes to [x0, x_new], so the root becomes the upper end
+   // x1, where it is the best end with f_min = 0 and the solver stops whatever the tolerances say.
+   if f_new == T::zero() || opposite_signs(f_new, f0) {
+       Ok((x0, x_new))
+   }
+   else if opposite_signs(f_new, f1) {
+       Ok((x_new, x1))
+   }
+   else {
+        // with f0 and f1 of opposite sign this can't happen, since f_new is finite, see the invariant above
+        Err(SolveError::NonBracketingInterval)
+   }
+}
+
+/** Whether a and b are nonzero and of opposite sign. This compares signs rather than testing a*b < 0, since the
+  * product underflows to zero when both are tiny, and overflows when both are huge. NaN is never of opposite sign.
+  */
+pub(crate) fn opposite_signs<T: Float>(a: T, b: T) -> bool {
+   (a < T::zero() && b > T::zero()) || (a > T::zero() && b < T::zero())
+}
+
+/** Standard bisection method */
+fn naive_bisection<T: Float> (x0: T, x1: T) -> T{
+   (x0 + x1)/constant(2.0)
+}
+
+/** The root of the secant through (x0, f0) and (x1, f1). It's written as a step from x0 by a fraction of the width,
+  * rather than as (x0*f1 - x1*f0)/(f1 - f0), whose products cancel when the bracket is far from the origin compared to
+  * its width. Equal values, or a difference that overflows, give the midpoint.
+  */
+fn false_position<T: Float> (x0: T, x1: T, f0: T, f1: T) -> T{
+   match safe_div(f0, f0 - f1) {
+       Some(frac) => x0 + frac*(x1 - x0),
+       None => naive_bisection(x0, x1)
+   }
+}
+
+/** False position with the Illinois modification: the function value at the end that keeps being retained is halved
+  * in the secant, which pulls the new point towards that end so the other end stops creeping towards the root.
+  * last_moved is the end that was moved by the last two steps.
+  */
+fn illinois_position<T: Float> (x0: T, x1: T, f0: T, f1: T, last_moved: CoordinateChangeFlag) -> T{
+   let half: T = constant(0.5);
+   let (g0, g1) = if last_moved == CoordinateChangeFlag::First { (f0, f1*half) } else { (f0*half, f1) };
+
+   false_position(x0, x1, g0, g1)
+}
+
+/** Whether the derivatives at the ends of the bracket can be trusted by the inverse cubic. The inverse has slope 1/f',
+  * so a (nearly) horizontal tangent blows up the interpolant. What counts as flat is measured against the slope of the
+  * secant across the bracket. A steep tangent is harmless, the inverse is just flat there.
+  */
+pub(crate) fn reliable_derivatives<T: Float>(x0: T, x1: T, f0: T, df0: T, f1: T, df1: T) -> bool {
+   let flat = ((f1 - f0)/(x1 - x0)).abs()*T::epsilon().sqrt();
+   df0.abs() > flat && df1.abs() > flat
+}
+
+/** Inverse quadratic interpolation through the ends of the bracket and the point (xp, fp) that was last dropped from it,
+  * which needs no derivatives. Returns None if the prediction falls outside the bracket, which includes the case where
+  * two of the function values are equal.
+  */
+fn inverse_quadratic<T: Float> (x0: T, x1: T, f0: T, f1: T, xp: T, fp: T) -> Option<T>{
+   let x_new = safe_div(xp*f0*f1, (fp - f0)*(fp - f1))? + safe_div(x0*fp*f1, (f0 - fp)*(f0 - f1))?
+             + safe_div(x1*fp*f0, (f1 - fp)*(f1 - f0))?;
+   interior(x0, x1, x_new)
+}
+
+/** A Newton step from whichever end of the bracket has the larger derivative, as that end gives the more reliable
+  * linearisation. Returns None if the step leaves the bracket, or both derivatives are zero.
+  */
+fn newton_bisection<T: Float> (x0: T, x1: T, f0: T, df0:T,  f1: T, df1: T) -> Option<T>{
+   let x_new = if df0.abs() > df1.abs() { x0 - safe_div(f0, df0)? } else { x1 - safe_div(f1, df1)? };
+   interior(x0, x1, x_new)
+}
+
+/** A Halley step x - 2ff'/(2f'^2 - ff'') from whichever end of the bracket has the larger derivative, which needs
+  * the second derivative at that end. None if the denominator vanishes.
+  */
+fn halley_step<T: Float, D2: Fn(T) -> T> (x0: T, x1: T, f0: T, df0:T,  f1: T, df1: T, deriv2: &D2) -> Option<T>{
+   let (x, f, df) = if df0.abs() > df1.abs() { (x0, f0, df0) } else { (x1, f1, df1) };
+   let two: T = constant(2.0);
+   Some(x - safe_div(two*f*df, two*df*df - f*deriv2(x))?)
+}
+
+/** A predicted point x_new, or None if the prediction falls outside the bracket */
+fn interior<T: Float> (x0: T, x1: T, x_new: T) -> Option<T>{
+   // NB: written so that a NaN prediction is also rejected
+   if x_new > x0 && x_new < x1 { Some(x_new) } else { None }
+}
+
+/** Search outwards from start for an interval [x0, x1] with f(x0)*f(x1) < 0.
+  * The search starts from [start - step, start + step] and repeatedly pushes out whichever end has the smaller
+  * |f|, growing the interval geometrically. Returns None if no sign change is found after max_expansions steps.
+  */
+pub fn find_bracket<T, F>(func: F, start: T, step: T, max_expansions: usize) -> Option<(T, T)>
+where
+    T: Float,
+    F: Fn(T) -> T
+{
+    let growth: T = constant(BRACKET_GROWTH);
+
+    let mut x0 = start - step.abs();
+    let mut x1 = start + step.abs();
+    let mut f0 = func(x0);
+    let mut f1 = func(x1);
+
+    for _ in 0..max_expansions {
+        if opposite_signs(f0, f1) {
+            return Some((x0, x1));
+        }
+        if f0.abs() < f1.abs() {
+            x0 = x0 + growth*(x0 - x1);
+            f0 = func(x0);
+        } else {
+            x1 = x1 + growth*(x1 - x0);
+            f1 = func(x1);
+        }
+    }
+
+    if opposite_signs(f0, f1) { Some((x0, x1)) } else { None }
+}
+
+/** The actual solver. Requires that f(x0) and f(x1) have opposite signs, and gives up after max_iters iterations.
+  * func and deriv can be plain functions or closures, and the solver works in either f32 or f64. The ends may be
+  * given in either order, and if x0 == x1 the result is x0 if it's an exact root, and NonBracketingInterval otherwise.
+  *
+  * An end where func is exactly zero, of either sign, is a valid bracket whatever the sign of func at the other end,
+  * so long as that is finite. That end is returned straight away as the root, without evaluating deriv or taking a
+  * step, and if both ends are roots the lower one is returned. Otherwise f(x0) and f(x1) must be nonzero and of
+  * opposite sign, and the root found is strictly inside the bracket unless the tolerance is met at an end.
+  */
+pub fn inv_cubic_solve<T, F, D>(x0: T, x1: T, tol: T, max_iters: usize, func: F, deriv: D) ->  Result<T, SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T
+{
+    inv_cubic_solve_with_callback(x0, x1, tol, max_iters, func, deriv, |_| {})
+}
+
+/** The integer nearest a root of func in [x0, x1], for when the root of a continuous relaxation is wanted as an
+  * integer. The root is found with the default Solver, then whichever of its floor and ceiling has the smaller
+  * |func| is returned. A tie, e.g. for a root exactly at k + 0.5 of a symmetric function, goes to the floor. A root
+  * beyond the range of i64 saturates to i64::MIN or i64::MAX.
+  */
+pub fn inv_cubic_solve_integer<T, F, D>(x0: T, x1: T, func: F, deriv: D) ->  Result<i64, SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T
+{
+    let root = Solver::new().solve(x0, x1, &func, deriv)?;
+    let (lo, hi) = (root.floor(), root.ceil());
+    let nearest = if func(hi).abs() < func(lo).abs() { hi } else { lo };
+    Ok(nearest.to_i64().unwrap_or(if nearest > T::zero() { i64::MAX } else { i64::MIN }))
+}
+
+/** Same as inv_cubic_solve, but func and deriv are trait objects, e.g. from a `Vec<Box<dyn Fn(f64) -> f64>>` of
+  * problems. Only one copy of the solver is compiled for each float type, however many different functions it's
+  * used with, at the cost of a dynamic call per evaluation.
+  */
+pub fn inv_cubic_solve_dyn<T: Float>(x0: T, x1: T, tol: T, max_iters: usize, func: &dyn Fn(T) -> T, deriv: &dyn Fn(T) -> T) ->  Result<T, SolveError<T>> {
+    inv_cubic_solve(x0, x1, tol, max_iters, func, deriv)
+}
+
+/** Same as inv_cubic_solve, but with separate absolute and relative tolerances. The method has converged once
+  * the bracket width is below abs_tol + rel_tol*|x|, or the residual is below abs_tol.
+  */
+pub fn inv_cubic_solve_rel<T, F, D>(x0: T, x1: T, abs_tol: T, rel_tol: T, max_iters: usize, func: F, deriv: D) ->  Result<T, SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T
+{
+    Solver::new().tol(abs_tol).rel_tol(rel_tol).max_iters(max_iters).solve(x0, x1, func, deriv)
+}
+
+/** Same as inv_cubic_solve, but finds x with func(x) = target rather than func(x) = 0, e.g. to invert a CDF.
+  * f(x0) - target and f(x1) - target must have opposite signs. The derivative is the same as that of func.
+  */
+pub fn inv_cubic_solve_for<T, F, D>(x0: T, x1: T, target: T, tol: T, max_iters: usize, func: F, deriv: D) ->  Result<T, SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T
+{
+    inv_cubic_solve(x0, x1, tol, max_iters, |x| func(x) - target, deriv)
+}
+
+/** Find the root of a monotone function from a single guess, see Solver::solve_monotone */
+pub fn solve_monotone<T, F, D>(guess: T, tol: T, func: F, deriv: D) ->  Result<T, SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T
+{
+    Solver::new().tol(tol).solve_monotone(guess, func, deriv)
+}
+
+/** Same as inv_cubic_solve, but func_deriv returns f(x) and f'(x) together, see Solver::solve_combined */
+pub fn inv_cubic_solve_combined<T, G>(x0: T, x1: T, tol: T, max_iters: usize, func_deriv: G) ->  Result<T, SolveError<T>>
+where
+    T: Float,
+    G: Fn(T) -> (T, T)
+{
+    Solver::new().tol(tol).max_iters(max_iters).solve_combined(x0, x1, func_deriv)
+}
+
+/** Same as inv_cubic_solve, but also uses the second derivative deriv2 to take Halley steps, see Solver::solve_halley */
+pub fn inv_cubic_solve_halley<T, F, D, D2>(x0: T, x1: T, tol: T, max_iters: usize, func: F, deriv: D, deriv2: D2) ->  Result<T, SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T,
+    D2: Fn(T) -> T
+{
+    Solver::new().tol(tol).max_iters(max_iters).solve_halley(x0, x1, func, deriv, deriv2)
+}
+
+/** Same as inv_cubic_solve, for a positive root, solved for ln(x) so that tol is effectively relative to x. See
+  * Solver::solve_log_scale.
+  */
+pub fn inv_cubic_solve_log_scale<T, F, D>(x0: T, x1: T, tol: T, max_iters: usize, func: F, deriv: D) ->  Result<T, SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T
+{
+    Solver::new().tol(tol).max_iters(max_iters).solve_log_scale(x0, x1, func, deriv)
+}
+
+/** Same as inv_cubic_solve, but returns the final bracket, which is guaranteed to contain a root, rather than a
+  * single point. See Solver::solve_bracket.
+  */
+pub fn inv_cubic_solve_bracket<T, F, D>(x0: T, x1: T, tol: T, max_iters: usize, func: F, deriv: D) ->  Result<(T, T), SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T
+{
+    Solver::new().tol(tol).max_iters(max_iters).solve_bracket(x0, x1, func, deriv)
+}
+
+/** Same as inv_cubic_solve, but for when the derivative of func is not available.
+  * The derivatives are estimated by central differences with a step of about cbrt(epsilon)*|x|, which costs two
+  * extra function evaluations per step. The error in the estimated derivatives means the convergence order
+  * is somewhat lower than with the exact derivative, although the bracketing safeguards are unaffected.
+  */
+pub fn inv_cubic_solve_no_deriv<T, F>(x0: T, x1: T, tol: T, max_iters: usize, func: F) ->  Result<T, SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> T
+{
+    inv_cubic_solve(x0, x1, tol, max_iters, &func, |x| central_difference(&func, x))
+}
+
+/** Estimate f'(x) by a central difference with a step of cbrt(epsilon)*max(|x|, 1) */
+fn central_difference<T: Float, F: Fn(T) -> T>(func: &F, x: T) -> T {
+    let h = T::epsilon().cbrt() * x.abs().max(T::one());
+    (func(x + h) - func(x - h))/(h + h)
+}
+
+/** Evaluate deriv at x, and if check_derivatives is set, compare the result against a central difference */
+#[inline]
+fn checked_deriv<T, F, D>(x: T, func: &F, deriv: &D, check_derivatives: Option<T>) -> Result<T, SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T
+{
+    let analytic = deriv(x);
+    if let Some(rel_tol) = check_derivatives {
+        let numeric = central_difference(func, x);
+        if analytic.is_nan() || (analytic - numeric).abs() > rel_tol*analytic.abs().max(numeric.abs()) {
+            return Err(SolveError::DerivativeMismatch { x, analytic, numeric });
+        }
+    }
+    Ok(analytic)
+}
+
+/** Find a root of func in [x0, x1] by the false position (regula falsi) method, with the same termination logic as
+  * inv_cubic_solve.  Plain false position can stall with one end of the bracket never moving, this is avoided by
+  * bisecting every 5 iterations, and whenever the same end of the bracket moves twice in a row.
+  */
+pub fn false_position_solve<T, F>(x0: T, x1: T, tol: T, max_iters: usize, func: F) ->  Result<T, SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> T
+{
+    let solver = Solver::new().tol(tol).max_iters(max_iters).cubic(false);
+    solver.solve(x0, x1, func, |_| T::nan())
+}
+
+/** Find a root of func in [x0, x1] by plain bisection, with the same termination logic as inv_cubic_solve. This is
+  * the slowest of the methods, but the bracket is guaranteed to halve at every step, so it's the baseline to compare
+  * the others against.
+  */
+pub fn bisection_solve<T, F>(x0: T, x1: T, tol: T, max_iters: usize, func: F) ->  Result<T, SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> T
+{
+    // with a frequency of 1 every step is a periodic bisection
+    let solver = Solver::new().tol(tol).max_iters(max_iters).cubic(false).bisection_freq(1);
+    solver.solve(x0, x1, func, |_| T::nan())
+}
+
+/** Same as inv_cubic_solve, but calls on_iter with the current state at the start of every iteration */
+pub fn inv_cubic_solve_with_callback<T, F, D, C>(x0: T, x1: T, tol: T, max_iters: usize, func: F, deriv: D, on_iter: C) ->  Result<T, SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T,
+    C: FnMut(&IterationInfo<T>)
+{
+    Solver::new().tol(tol).max_iters(max_iters).solve_with_callback(x0, x1, func, deriv, on_iter)
+}
+
+/** Same as inv_cubic_solve, but also reports how much work was done. Hitting the iteration cap is not an error
+  * here, instead the best point is returned with converged set to false in the stats.
+  */
+pub fn inv_cubic_solve_with_stats<T, F, D>(x0: T, x1: T, tol: T, max_iters: usize, func: F, deriv: D) ->  Result<(T, SolveStats), SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T
+{
+    Solver::new().tol(tol).max_iters(max_iters).solve_with_stats(x0, x1, func, deriv)
+}
+
+/** Reusable storage for Solver::solve_with_scratch, holding the history of the last solve */
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Default)]
+pub struct SolveScratch<T = f64> {
+   history: Vec<BracketState<T>>
+}
+
+#[cfg(feature = "std")]
+impl<T> SolveScratch<T> {
+   /** Empty storage, which grows as needed on the first solves */
+   pub fn new() -> Self {
+       SolveScratch { history: Vec::new() }
+   }
+
+   /** The state at the start of every iteration of the last solve, as for Solver::solve_with_history */
+   pub fn history(&self) -> &[BracketState<T>] {
+       &self.history
+   }
+}
+
+/** The state of the solver between steps: the current bracket, along with the function values and derivatives at
+  * its ends. The best end and the residuals are kept up to date by the solver as it steps, so changing f0 or f1 by
+  * hand won't be reflected in x_best, f_min or f_max.
+  */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BracketState<T = f64> {
+   /// iteration number, starting from 1 for the initial bracket
+   pub n_iters: usize,
+   /// lower end of the bracket
+   pub x0: T,
+   /// upper end of the bracket
+   pub x1: T,
+   /// f(x0)
+   pub f0: T,
+   /// f(x1)
+   pub f1: T,
+   /// f'(x0), NaN if the derivative isn't needed by the selected steps
+   pub df0: T,
+   /// f'(x1), NaN if the derivative isn't needed by the selected steps
+   pub df1: T,
+   /// the kind of step that produced this bracket, None for the initial bracket
+   pub step: Option<StepKind>,
+   // which end of the bracket was moved by the last step, and whether the next step must be a bisection
+   last_coord_changed: CoordinateChangeFlag,
+   should_bisect: bool,
+   // whether the last step was an Illinois step, if that also kept the same end, a bisection follows
+   illinois: bool,
+   // the last end to be replaced and the function value there, for inverse quadratic interpolation
+   dropped: Option<(T, T)>,
+   // the iteration and the width at the start of the current window of bisection_freq steps, for guaranteed_shrink
+   window_start: usize,
+   window_width: T,
+   // which end of the bracket has the smaller residual, and the smaller and larger of |f0| and |f1|
+   best: CoordinateChangeFlag,
+   f_min: T,
+   f_max: T,
+   // the point with the smallest residual seen so far, which may have since been dropped from the bracket
+   x_global: T,
+   f_global: T,
+   // how many brackets in a row had derivatives contradicting the secant, and whether that ever went on for too long
+   contradicting: usize,
+   suspicious: bool
+}
+
+impl<T: Float> BracketState<T> {
+   /** Evaluate the function (and the derivative, if needed) at both ends of the bracket, and check that it does
+     * bracket a root. The ends may be given in either order, the state always has x0 <= x1.
+     */
+   fn new<F, D>(x0: T, x1: T, func: &F, deriv: &D, needs_deriv: bool, check_derivatives: Option<T>) -> Result<Self, SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T
+   {
+       // the steps all assume x0 < x1, e.g. when checking that a predicted point lies inside the bracket
+       let (x0, x1) = if x1 < x0 { (x1, x0) } else { (x0, x1) };
+
+       // a bracket of zero width is only accepted if x0 is an exact root, otherwise f0 == f1 and the sign check
+       // below rejects it. Either way the function only needs evaluating once.
+       let f0 = func(x0);
+       let f1 = if x1 == x0 { f0 } else { func(x1) };
+
+       // an endpoint that is an exact root needs no further work, so the derivatives aren't needed either
+       let exact_root = BracketState::check_ends(x0, x1, f0, f1)?;
+       let (df0, df1) = if needs_deriv && !exact_root {
+           (checked_deriv(x0, func, deriv, check_derivatives)?, checked_deriv(x1, func, deriv, check_derivatives)?)
+       } else {
+           (T::nan(), T::nan())
+       };
+
+       Ok(BracketState::from_ends(x0, x1, f0, f1, df0, df1))
+   }
+
+   /** Check the function values f0 and f1 at the ends x0 <= x1 of a new bracket: they must be finite, and of opposite
+     * sign unless one is zero, in which case the other may have either sign. Returns whether either end is an exact root.
+     */
+   pub(crate) fn check_ends(x0: T, x1: T, f0: T, f1: T) -> Result<bool, SolveError<T>> {
+       if !f0.is_finite() {
+           return Err(SolveError::NonFiniteFunctionValue { x: x0 });
+       }
+       if !f1.is_finite() {
+           return Err(SolveError::NonFiniteFunctionValue { x: x1 });
+       }
+
+       let exact_root = f0 == T::zero() || f1 == T::zero();
+       if !exact_root && f0.signum() == f1.signum() {
+           return Err(SolveError::NonBracketingInterval);
+       }
+       Ok(exact_root)
+   }
+
+   /** The state before any steps are taken, from the values at the ends of a bracket that passed check_ends */
+   pub(crate) fn from_ends(x0: T, x1: T, f0: T, f1: T, df0: T, df1: T) -> Self {
+       // if both ends are exact roots, the lower one is the root
+       let best = if f0.abs() < f1.abs() || f0 == T::zero() { CoordinateChangeFlag::First } else { CoordinateChangeFlag::Second };
+       let f_min = f0.abs().min(f1.abs());
+
+       let mut state = BracketState {
+           n_iters: 1,
+           x0, x1, f0, f1, df0, df1,
+           step: None,
+           last_coord_changed: CoordinateChangeFlag::Reset,
+           should_bisect: false,
+           illinois: false,
+           dropped: None,
+           window_start: 1,
+           window_width: (x1 - x0).abs(),
+           best,
+           f_min,
+           f_max: f0.abs().max(f1.abs()),
+           x_global: if best == CoordinateChangeFlag::First { x0 } else { x1 },
+           f_global: f_min,
+           contradicting: 0,
+           suspicious: false
+       };
+       state.check_derivative_signs();
+       state
+   }
+
+   /** The end of the bracket with the smaller residual */
+   pub fn x_best(&self) -> T {
+       if self.best == CoordinateChangeFlag::First {self.x0} else {self.x1}
+   }
+
+   /** f'(x_best), exactly as deriv returned it there, NaN if the derivative isn't needed by the selected steps */
+   pub fn df_best(&self) -> T {
+       if self.best == CoordinateChangeFlag::First {self.df0} else {self.df1}
+   }
+
+   /** min(|f(x0)|, |f(x1)|) */
+   pub fn f_min(&self) -> T {
+       self.f_min
+   }
+
+   /** max(|f(x0)|, |f(x1)|) */
+   pub fn f_max(&self) -> T {
+       self.f_max
+   }
+
+   /** The point the solver returns: the best end of the bracket, unless a point dropped from it earlier had a smaller
+     * residual, e.g. when the last step overshot
+     */
+   pub(crate) fn root(&self) -> T {
+       if self.f_global < self.f_min { self.x_global } else { self.x_best() }
+   }
+
+   /** The point for Solver::polish: a Newton step from the best end, or a secant step if the derivative there isn't
+     * known. None if it leaves the bracket or is already one of its ends.
+     */
+   fn polish_point(&self) -> Option<T> {
+       let f_best = if self.best == CoordinateChangeFlag::First {self.f0} else {self.f1};
+       let x = match safe_div(f_best, self.df_best()) {
+           Some(step) => self.x_best() - step,
+           None => false_position(self.x0, self.x1, self.f0, self.f1)
+       };
+       if x >= self.x0 && x <= self.x1 && self.moves_end(x) { Some(x) } else { None }
+   }
+
+   /** Whether the derivatives at the two ends have opposite signs. With f0 and f1 of opposite sign, a function that
+     * crosses zero once, monotonically, has derivatives of the same sign at both ends, so this suggests the bracket also
+     * contains a turning point, where the inverse of f isn't single valued and the inverse cubic is a poor guide.
+     * The solver still converges, it just leans on the bisection and false position steps. False if either
+     * derivative isn't known.
+     */
+   pub fn derivative_signs_differ(&self) -> bool {
+       opposite_signs(self.df0, self.df1)
+   }
+
+   /** The point with the smallest residual of all those evaluated so far. This is usually x_best, but a step can
+     * drop the best end from the bracket when the new point has the same sign and a larger residual.
+     */
+   pub fn x_best_global(&self) -> T {
+       self.x_global
+   }
+
+   /** The residual |f| at x_best_global */
+   pub fn f_best_global(&self) -> T {
+       self.f_global
+   }
+
+   /** Update the best end and the residuals after one end of the bracket has moved and f there is now f_new.
+     * The residual at the other end is already known, it's f_min if that end was the best one, and f_max otherwise.
+     * Ties go to x1, as when the state is first set up.
+     */
+   fn update_best(&mut self, moved: CoordinateChangeFlag, f_new: T) {
+       let f_moved = f_new.abs();
+       let f_other = if moved == self.best { self.f_max } else { self.f_min };
+       let moved_is_best = match moved {
+           CoordinateChangeFlag::First => f_moved < f_other,
+           _ => f_moved <= f_other
+       };
+       if moved_is_best {
+           self.best = moved;
+           self.f_min = f_moved;
+           self.f_max = f_other;
+       } else {
+           self.best = if moved == CoordinateChangeFlag::First { CoordinateChangeFlag::Second } else { CoordinateChangeFlag::First };
+           self.f_min = f_other;
+           self.f_max = f_moved;
+       }
+   }
+
+   /** How many brackets in a row, up to this one, had derivatives at both ends of the opposite sign to the secant,
+     * so that the inverse cubic is told func runs the other way from what its values say
+     */
+   pub fn contradicting_derivatives(&self) -> usize {
+       self.contradicting
+   }
+
+   /** Whether, after any step of the solve so far, the derivatives had contradicted the secant for more brackets in a
+     * row than Solver::suspicious_brackets allows. The inverse cubic then points the wrong way, and the solver converges slowly by bisection and
+     * false position instead. Near a simple root that can't last, so the usual cause is a deriv that doesn't match
+     * func, e.g. with the wrong sign. A bracket around several roots can do it too.
+     */
+   pub fn derivative_suspicious(&self) -> bool {
+       self.suspicious
+   }
+
+   /** Update the count of brackets whose derivatives contradict the secant. NaN derivatives, when the solve doesn't
+     * need them, never do.
+     */
+   fn check_derivative_signs(&mut self) {
+       let secant = self.f1 - self.f0;
+       if opposite_signs(self.df0, secant) && opposite_signs(self.df1, secant) {
+           self.contradicting += 1;
+       } else {
+           self.contradicting = 0;
+       }
+   }
+
+   /** Whether there are no floating point numbers strictly between the ends, so the bracket can't shrink any further */
+   pub fn at_machine_precision(&self) -> bool {
+       let mid = (self.x0 + self.x1)/constant(2.0);
+       mid == self.x0 || mid == self.x1
+   }
+
+   /** Width of the bracket */
+   pub fn width(&self) -> T {
+       (self.x1 - self.x0).abs()
+   }
+
+   /** The cubic through the ends of the bracket from two_point_cubic, e.g. to plot against func. The derivatives at
+     * the ends are NaN, and so is the cubic, if the solve didn't need them.
+     */
+   pub fn interpolating_cubic(&self) -> impl Fn(T) -> T {
+       let BracketState { x0, x1, f0, f1, df0, df1, .. } = *self;
+       move |x| two_point_cubic(x, x0, x1, f0, df0, f1, df1)
+   }
+
+   /** The inverse cubic from two_point_cubic_inverse_at, giving x for a value of f. Its value at 0 is the prediction of
+     * the inverse cubic step, before it is checked against the bracket.
+     */
+   pub fn inverse_cubic(&self) -> impl Fn(T) -> T {
+       let BracketState { x0, x1, f0, f1, df0, df1, .. } = *self;
+       move |target| two_point_cubic_inverse_at(target, x0, x1, f0, df0, f1, df1)
+   }
+
+   /** n points (x, p(x)) of interpolating_cubic, evenly spaced from x0 to x1 inclusive */
+   #[cfg(feature = "std")]
+   pub fn sample_cubic(&self, n: usize) -> Vec<(T, T)> {
+       let cubic = self.interpolating_cubic();
+       let last = T::from(n.saturating_sub(1).max(1)).unwrap();
+       (0..n).map(|i| {
+           let x = self.x0 + (self.x1 - self.x0)*T::from(i).unwrap()/last;
+           (x, cubic(x))
+       }).collect()
+   }
+
+   fn info(&self) -> IterationInfo<T> {
+       IterationInfo { n_iters: self.n_iters, x0: self.x0, x1: self.x1, f_min: self.f_min(), f_max: self.f_max(), dx: self.width(), step: self.step }
+   }
+
+   /** Take a single step, returning the new state. If the second derivative is given, a Halley step is tried first. */
+   #[inline]
+   fn advance<F, D, D2, P>(&self, cfg: &Solver<T>, func: &F, deriv: &D, deriv2: Option<&D2>, predictor: &P) -> Result<Self, SolveError<T>>
+   where
+       F: Fn(T) -> T,
+       D: Fn(T) -> T,
+       D2: Fn(T) -> T,
+       P: Predictor<T> + ?Sized
+   {
+       let proposal = self.propose(cfg, deriv2, predictor);
+       let f_new = evaluate(proposal.x, func)?;
+
+       // derivatives are only evaluated if they are going to be used, at a point that becomes an end of the bracket
+       let df_new = if self.moves_end(proposal.x) && (cfg.needs_deriv() || deriv2.is_some()) {
+           checked_deriv(proposal.x, func, deriv, cfg.check_derivatives)?
+       } else {
+           T::nan()
+       };
+       self.accept(cfg, proposal, f_new, df_new)
+   }
+
+   /** Choose the next point to evaluate. This only needs the current state, along with the second derivative at an end
+     * for a Halley step, so the evaluation of func can happen elsewhere, e.g. asynchronously.
+     */
+   pub(crate) fn propose<D2, P>(&self, cfg: &Solver<T>, deriv2: Option<&D2>, predictor: &P) -> Proposal<T>
+   where
+       D2: Fn(T) -> T,
+       P: Predictor<T> + ?Sized
+   {
+       let BracketState { n_iters, x0, x1, f0, f1, df0, df1, .. } = *self;
+
+       // perform bisection every nth iteration, or if a point hasn't been changed in two iterations.
+       // NB: is_multiple_of(0) is false for n_iters >= 1, so a frequency of 0 never triggers.
+       let periodic_bisection = cfg.strategy == Strategy::Robust && n_iters.is_multiple_of(cfg.bisection_freq);
+       if self.should_bisect && !periodic_bisection && self.step == Some(StepKind::FalsePosition) && !self.illinois {
+           // false position has kept the same end twice, rather than bisecting, use the Illinois modification
+           let x = illinois_position(x0, x1, f0, f1, self.last_coord_changed);
+           return self.hooked(cfg, predictor, x, StepKind::FalsePosition, true);
+       }
+       if periodic_bisection || self.should_bisect {
+           return Proposal { x: naive_bisection(x0, x1), step: StepKind::Bisection, illinois: false };
+       }
+
+       let mut result = deriv2.and_then(|deriv2| halley_step(x0, x1, f0, df0, f1, df1, deriv2))
+                              .and_then(|x| interior(x0, x1, x))
+                              .map(|x| (x, StepKind::Halley));
+       // if the predictor rejects the bracket, e.g. the inverse cubic when either derivative is too flat compared
+       // to the secant, inverse quadratic interpolation with the last point dropped from the bracket is used instead
+       if result.is_none() && cfg.cubic {
+           result = match predictor.predict(x0, x1, f0, df0, f1, df1) {
+               Some(x_new) => interior(x0, x1, x_new).map(|x| (x, StepKind::InverseCubic)),
+               None => self.dropped.and_then(|(xp, fp)| inverse_quadratic(x0, x1, f0, f1, xp, fp))
+                                   .map(|x| (x, StepKind::InverseQuadratic))
+           };
+       }
+       if result.is_none() && cfg.newton_fallback {
+           result = newton_bisection(x0, x1, f0, df0, f1, df1).map(|x| (x, StepKind::Newton));
+       }
+       let (x, step) = result.unwrap_or_else(|| (false_position(x0, x1, f0, f1), StepKind::FalsePosition));
+       self.hooked(cfg, predictor, x, step, false)
+   }
+
+   /** The proposal for x after the perturbation and the predictor's accept_step, which bisects if it gives None or a
+     * point outside the bracket
+     */
+   fn hooked<P: Predictor<T> + ?Sized>(&self, cfg: &Solver<T>, predictor: &P, x: T, step: StepKind, illinois: bool) -> Proposal<T> {
+       match predictor.accept_step(self.perturbed(cfg, x), self) {
+           Some(x) if x >= self.x0 && x <= self.x1 => Proposal { x, step, illinois },
+           _ => Proposal { x: naive_bisection(self.x0, self.x1), step: StepKind::Bisection, illinois: false }
+       }
+   }
+
+   /** x moved inwards if it is an end of the bracket to within rounding and cfg has a perturbation */
+   fn perturbed(&self, cfg: &Solver<T>, x: T) -> T {
+       let perturbation = match cfg.perturbation {
+           Some(perturbation) => perturbation,
+           None => return x
+       };
+       let at = |end: T| (x - end).abs() <= T::epsilon()*end.abs().max(T::min_positive_value());
+       let shift = perturbation*(self.x1 - self.x0);
+       let moved = if at(self.x0) {
+           self.x0 + shift
+       } else if at(self.x1) {
+           self.x1 - shift
+       } else {
+           return x;
+       };
+       // in a bracket only a few floats wide the shift rounds away, and the midpoint is the only point left to try
+       interior(self.x0, self.x1, moved).unwrap_or_else(|| naive_bisection(self.x0, self.x1))
+   }
+
+   /** Whether x would become an end of the bracket, i.e. it isn't one already, so the derivative there is needed */
+   pub(crate) fn moves_end(&self, x: T) -> bool {
+       x != self.x0 && x != self.x1
+   }
+
+   /** The state after evaluating the proposal, where f_new = f(proposal.x) and df_new is the derivative there, or NaN
+     * if the derivative isn't needed
+     */
+   pub(crate) fn accept(&self, cfg: &Solver<T>, proposal: Proposal<T>, f_new: T, df_new: T) -> Result<Self, SolveError<T>> {
+       let BracketState { n_iters, x0, x1, f0, f1, .. } = *self;
+       let Proposal { x: x_new, step, illinois } = proposal;
+       let mut next = *self;
+
+       next.step = Some(step);
+       next.illinois = illinois;
+       if illinois {
+           next.should_bisect = false;
+       }
+       if step == StepKind::Bisection {
+           next.should_bisect = false;
+           // reset the flag to tell if a point hasn't changed in two iterations
+           next.last_coord_changed = CoordinateChangeFlag::Reset;
+       }
+
+       let x = split_bracket(x0, x1, f0, f1, x_new, f_new)?;
+
+       // update the function values and derivatives depending on which point was changed
+       // and determine if the same point was changed twice in a row
+       let second_last_coord_changed = next.last_coord_changed;
+       if x0 != x.0 {
+           next.dropped = Some((x0, f0));
+           next.x0 = x.0;
+           next.f0 = f_new;
+           next.df0 = df_new;
+           next.last_coord_changed = CoordinateChangeFlag::First;
+       } else if x1 != x.1 {
+           next.dropped = Some((x1, f1));
+           next.x1 = x.1;
+           next.f1 = f_new;
+           next.df1 = df_new;
+           next.last_coord_changed = CoordinateChangeFlag::Second;
+       } else {
+           // the new point is already an end of the bracket, e.g. once the ends are adjacent floats, or when the root
+           // is within rounding of an end. There is nothing to update, and the same step would only land there
+           // again, so bisect next.
+           next.should_bisect = true;
+           next.n_iters = n_iters + 1;
+           return Ok(next);
+       };
+
+       next.update_best(next.last_coord_changed, f_new);
+       next.check_derivative_signs();
+       next.suspicious |= next.contradicting > cfg.suspicious_brackets;
+       if next.f_min < next.f_global {
+           next.x_global = next.x_best();
+           next.f_global = next.f_min;
+       }
+
+       if next.last_coord_changed == second_last_coord_changed
+          && (cfg.strategy == Strategy::Robust || next.width() > cfg.guaranteed_shrink*self.width()) {
+           next.should_bisect = true;
+       }
+
+       // a cubic step that hardly shrinks the bracket is followed by a bisection
+       if step == StepKind::InverseCubic && next.width() > cfg.accept_frac*self.width() {
+           next.should_bisect = true;
+       }
+
+       // at the end of each window, keep bisecting until the bracket has shrunk by guaranteed_shrink over the window
+       if cfg.bisection_freq > 0 && n_iters + 1 - next.window_start >= cfg.bisection_freq {
+           if next.width() > cfg.guaranteed_shrink*next.window_width {
+               next.should_bisect = true;
+           } else {
+               next.window_start = n_iters + 1;
+               next.window_width = next.width();
+           }
+       }
+
+       next.n_iters = n_iters + 1;
+       Ok(next)
+   }
+}
+
+/** Take exactly one step of the solver from state, with the settings in cfg, returning the new state. Together with
+  * Solver::initial_state this allows an outer loop of one's own, e.g. to interleave the solve with other work. Only the
+  * step settings of cfg are used, it's up to the caller when to stop, e.g. with `cfg.is_converged(s.x0, s.x1, s.f0, s.f1)`.
+  */
+pub fn solve_step<T, F, D>(state: &BracketState<T>, cfg: &Solver<T>, func: &F, deriv: &D) -> Result<BracketState<T>, SolveError<T>>
+where
+    T: Float,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T
+{
+    state.advance(cfg, func, deriv, None::<&fn(T) -> T>, &CubicPredictor)
+}
+
+/** Drives the solver one step at a time, for visualisation or custom stopping rules. Each call to next() takes exactly
+  * one step and returns the new bracket, the starting bracket is available from state() before the first call.
+  * There is no convergence test, the iteration only ends once an exact root is found, or a step fails, in which
+  * case the error is available from error().
+  */
+pub struct SolveIter<T, F, D, D2 = fn(T) -> T, P = CubicPredictor> {
+   cfg: Solver<T>,
+   func: F,
+   deriv: D,
+   deriv2: Option<D2>,
+   predictor: P,
+   state: BracketState<T>,
+   error: Option<SolveError<T>>
+}
+
+impl<T, F, D> SolveIter<T, F, D>
+where
+    T: Float,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T
+{
+   /** Start solving func on the bracket [x0, x1], using the steps selected in cfg */
+   pub fn new(cfg: Solver<T>, x0: T, x1: T, func: F, deriv: D) -> Result<Self, SolveError<T>> {
+       SolveIter::build(cfg, x0, x1, func, deriv, None)
+   }
+}
+
+impl<T, F, D, D2> SolveIter<T, F, D, D2>
+where
+    T: Float,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T,
+    D2: Fn(T) -> T
+{
+   /** Same as new, but with the second derivative of func, so that Halley steps can be taken */
+   pub fn with_second_deriv(cfg: Solver<T>, x0: T, x1: T, func: F, deriv: D, deriv2: D2) -> Result<Self, SolveError<T>> {
+       SolveIter::build(cfg, x0, x1, func, deriv, Some(deriv2))
+   }
+
+   fn build(cfg: Solver<T>, x0: T, x1: T, func: F, deriv: D, deriv2: Option<D2>) -> Result<Self, SolveError<T>> {
+       let state = BracketState::new(x0, x1, &func, &deriv, cfg.needs_deriv() || deriv2.is_some(), cfg.check_derivatives)?;
+       Ok(SolveIter { cfg, func, deriv, deriv2, predictor: CubicPredictor, state, error: None })
+   }
+}
+
+impl<T, F, D, D2, P> SolveIter<T, F, D, D2, P>
+where
+    T: Float,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T,
+    D2: Fn(T) -> T,
+    P: Predictor<T>
+{
+   /** Use predictor for the interpolation steps from here on, instead of the inverse cubic */
+   pub fn predictor<Q: Predictor<T>>(self, predictor: Q) -> SolveIter<T, F, D, D2, Q> {
+       let SolveIter { cfg, func, deriv, deriv2, state, error, .. } = self;
+       SolveIter { cfg, func, deriv, deriv2, predictor, state, error }
+   }
+
+   /** The current bracket */
+   pub fn state(&self) -> &BracketState<T> {
+       &self.state
+   }
+
+   /** The error that ended the iteration, if any */
+   pub fn error(&self) -> Option<SolveError<T>> {
+       self.error
+   }
+}
+
+impl<T, F, D, D2, P> Iterator for SolveIter<T, F, D, D2, P>
+where
+    T: Float,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T,
+    D2: Fn(T) -> T,
+    P: Predictor<T>
+{
+   type Item = BracketState<T>;
+
+   fn next(&mut self) -> Option<BracketState<T>> {
+       // once an exact root is found the bracket can't be improved on
+       if self.error.is_some() || self.state.f_min() == T::zero() {
+           return None;
+       }
+       match self.state.advance(&self.cfg, &self.func, &self.deriv, self.deriv2.as_ref(), &self.predictor) {
+           Ok(state) => {
+               self.state = state;
+               Some(state)
+           },
+           Err(e) => {
+               self.error = Some(e);
+               None
+           }
+       }
+   }
+}
+
+/** Everything solve_core knows at the end of a solve */
+struct Solution<T> {
+   root: T,
+   state: BracketState<T>,
+   stats: SolveStats
+}
+
+impl<T: Copy> Solution<T> {
+   /** The solution itself if it converged, otherwise DidNotConverge with the best point found */
+   fn into_result(self) -> Result<Solution<T>, SolveError<T>> {
+       if self.stats.converged { Ok(self) } else { Err(SolveError::DidNotConverge { best: self.root }) }
+   }
+}
+
+/** Where solve_core starts from: a bracket whose ends still need evaluating, or a state that is already set up */
+enum Start<T> {
+    Bracket(T, T),
+    State(BracketState<T>)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn solve_core<T, Cv, F, D, D2, P, C>(cfg: &Solver<T>, criterion: &Cv, start: Start<T>, func: F, deriv: D, deriv2: Option<D2>, predictor: P, mut on_iter: C) ->  Result<Solution<T>, SolveError<T>>
+where
+    T: Float,
+    Cv: Converged<T> + ?Sized,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T,
+    D2: Fn(T) -> T,
+    P: Predictor<T>,
+    C: FnMut(&BracketState<T>) -> bool
+{
+    // count every evaluation, including those made inside the step helpers
+    let func_evals = Cell::new(0);
+    let deriv_evals = Cell::new(0);
+    let func = |x: T| { func_evals.set(func_evals.get() + 1); func(x) };
+    let deriv2_evals = Cell::new(0);
+    let deriv = |x: T| { deriv_evals.set(deriv_evals.get() + 1); deriv(x) };
+    let deriv2_evals = &deriv2_evals;
+    let deriv2 = deriv2.map(|deriv2| move |x: T| { deriv2_evals.set(deriv2_evals.get() + 1); deriv2(x) });
+    let solution = |state: BracketState<T>, converged: bool| Solution {
+        root: state.root(),
+        state,
+        stats: SolveStats {
+            iterations: state.n_iters - 1,
+            func_evals: func_evals.get(),
+            deriv_evals: deriv_evals.get(),
+            deriv2_evals: deriv2_evals.get(),
+            converged,
+            last_step: state.step,
+            derivative_suspicious: state.derivative_suspicious()
+        }
+    };
+
+    // the clock starts before the ends of the bracket are evaluated, as they are part of the solve
+    #[cfg(feature = "std")]
+    let deadline = cfg.timeout.map(|timeout| std::time::Instant::now() + timeout);
+
+    let mut iter = match start {
+        Start::Bracket(x0, x1) => SolveIter::build(*cfg, x0, x1, &func, &deriv, deriv2)?,
+        Start::State(state) => SolveIter { cfg: *cfg, func: &func, deriv: &deriv, deriv2, predictor: CubicPredictor, state, error: None }
+    }.predictor(predictor);
+
+    // the residual tolerance relative to the initial bracket, if there is one
+    let rel_residual = cfg.rel_residual.map(|r| r*iter.state().f_max());
+
+    /* 
+       NB: The loop below will terminate so long as f(x0) and f(x1) are of opposite sign.  In the worst case, the width will halve every bisection_freq iterations.
+       If periodic bisection is turned off, the only safeguard left is the bisection forced by moving the same point twice in a row
+       (or the Illinois step, if it was false position that moved it).
+       A tolerance that can't be met in floating point (e.g. tol = 0.0) ends with MachinePrecisionReached once the ends are adjacent
+       floats, and the iteration cap is there for ill-behaved functions.
+    */
+    // the best point from the last iteration and how many iterations in a row it has barely moved
+    let mut last_best = None;
+    let mut n_stagnant = 0;
+
+    loop{
+        let state = *iter.state();
+
+        // report the current state, and find out whether to carry on
+        let keep_going = on_iter(&state);
+
+        // if the method has converged, return the best point.  If an end of the bracket is an exact root, f_min is zero
+        // and x_best is that point, which is returned whatever the criterion says.
+        let converged = criterion.is_converged(state.x0, state.x1, state.f0, state.f1);
+        let converged = match (cfg.tol_combine, rel_residual) {
+            (_, None) => converged,
+            (TolCombine::Or, Some(tol)) => converged || state.f_min() < tol,
+            (TolCombine::And, Some(tol)) => converged && state.f_min() < tol
+        };
+        if state.f_min() == T::zero() || converged {
+            let polished = if cfg.polish && state.f_min() > T::zero() && cfg.func_eval_budget.is_none_or(|budget| func_evals.get() < budget) {
+                state.polish_point().map(|x| (x, func(x)))
+            } else {
+                None
+            };
+            let mut solution = solution(state, true);
+            if let Some((x, f)) = polished {
+                if f.abs() < state.f_min().min(state.f_best_global()) {
+                    solution.root = x;
+                }
+            }
+            return Ok(solution);
+        }
+
+        // stop rather than spin on a bracket that can't get any narrower
+        if state.at_machine_precision() {
+            return Err(SolveError::MachinePrecisionReached { best: state.root() });
+        }
+
+        // give up if the iteration budget is exhausted
+        if state.n_iters > cfg.max_iters {
+            return Ok(solution(state, false));
+        }
+
+        if !keep_going {
+            return Err(SolveError::Cancelled { best: state.root() });
+        }
+
+        #[cfg(feature = "std")]
+        if deadline.is_some_and(|deadline| std::time::Instant::now() >= deadline) {
+            return Err(SolveError::TimedOut { best: state.root() });
+        }
+
+        if let Some((stagnation_tol, k)) = cfg.stagnation {
+            let x_best = state.x_best();
+            match last_best {
+                Some(last) if (x_best - last).abs() < stagnation_tol => n_stagnant += 1,
+                _ => n_stagnant = 0
+            }
+            if n_stagnant >= k {
+                return Err(SolveError::Stagnated { best: state.root() });
+            }
+            last_best = Some(x_best);
+        }
+
+        // a step evaluates func once, and twice more around every derivative that is checked
+        if let Some(budget) = cfg.func_eval_budget {
+            let checks = if cfg.check_derivatives.is_some() && cfg.needs_deriv() { 2 } else { 0 };
+            if func_evals.get() + 1 + checks > budget {
+                return Err(SolveError::BudgetExhausted { best: state.root() });
+            }
+        }
+
+        if iter.next().is_none() {
+            if let Some(e) = iter.error() {
+                return Err(e);
+            }
+        }
+    }   
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // jumps from -1 to 1 at 0.3 without a root, so with tol = 0 the bracket keeps shrinking around the jump
+    fn jump(x: f64) -> f64 {
+        if x < 0.3 { -1.0 } else { 1.0 }
+    }
+
+    #[test]
+    fn iteration_cap_stops_a_solve_that_cannot_converge() {
+        match inv_cubic_solve(0.0, 1.0, 0.0, 10, jump, |_| 0.0) {
+            Err(SolveError::DidNotConverge { best }) => assert!((best - 0.3).abs() < 1e-2, "best = {}", best),
+            result => panic!("expected DidNotConverge, got {:?}", result)
+        }
+
+        let (_, stats) = inv_cubic_solve_with_stats(0.0, 1.0, 0.0, 10, jump, |_| 0.0).unwrap();
+        assert!(!stats.converged);
+        assert_eq!(stats.iterations, 10);
+    }
+
+    #[test]
+    fn f32_and_f64_find_the_same_root() {
+        let root64 = inv_cubic_solve(1.0f64, 2.0, 1e-12, 100, |x| x*x*x - 2.0, |x| 3.0*x*x).unwrap();
+        let root32 = inv_cubic_solve(1.0f32, 2.0, 1e-5, 100, |x| x*x*x - 2.0, |x| 3.0*x*x).unwrap();
+        assert!((root64 - 2f64.cbrt()).abs() < 1e-12);
+        assert!((f64::from(root32) - root64).abs() < 1e-5);
+
+        // the default tolerance follows the epsilon of the type
+        let root32 = Solver::<f32>::new().solve(1.0, 2.0, |x| x*x*x - 2.0, |x| 3.0*x*x).unwrap();
+        assert!((f64::from(root32) - root64).abs() < f64::from(f32::EPSILON.sqrt()));
+    }
+
+    #[test]
+    fn no_deriv_finds_the_same_root_as_the_analytic_derivative() {
+        let func = |x: f64| x.sin() + x.powi(3) - 0.5;
+        let exact = inv_cubic_solve(0.0, 1.0, 1e-12, 100, func, |x| x.cos() + 3.0*x*x).unwrap();
+        let estimated = inv_cubic_solve_no_deriv(0.0, 1.0, 1e-12, 100, func).unwrap();
+        assert!((exact - estimated).abs() < 1e-10, "{} vs {}", exact, estimated);
+        assert!(func(estimated).abs() < 1e-10);
+    }
+
+    #[test]
+    fn linear_function_takes_one_step() {
+        let calls = Cell::new(0);
+        let func = |x: f64| { calls.set(calls.get() + 1); 2.0*x - 1.0 };
+        let (root, stats) = inv_cubic_solve_with_stats(0.0, 3.0, 1e-12, 100, func, |_| 2.0).unwrap();
+        assert_eq!(root, 0.5);
+        assert!(stats.converged);
+        // both ends, then the root the inverse cubic predicts exactly
+        assert_eq!((stats.iterations, stats.func_evals, stats.deriv_evals), (1, 3, 3));
+        assert_eq!(stats.func_evals, calls.get());
+    }
+
+    #[test]
+    fn relative_tolerance_suits_a_large_root() {
+        // the root is 3e-11 above a, between a and the next float up, and func is never exactly zero
+        let a = 1202604.0;
+        let func = |x: f64| { let u = x - a; u + 1e-6*u*u - 3e-11 };
+        let deriv = |x: f64| 1.0 + 2e-6*(x - a);
+
+        // floats near the root are 2.3e-10 apart, and the residual never gets below 3e-11, so an absolute tolerance
+        // of 1e-12 can't be met
+        let result = inv_cubic_solve(1e6, 2e6, 1e-12, 200, func, deriv);
+        assert!(matches!(result, Err(SolveError::DidNotConverge { .. }) | Err(SolveError::MachinePrecisionReached { .. })), "{:?}", result);
+
+        let root = inv_cubic_solve_rel(1e6, 2e6, 0.0, 1e-12, 200, func, deriv).unwrap();
+        assert!((root - a).abs() < 1e-12*a);
+    }
+
+    #[test]
+    fn default_solver_matches_inv_cubic_solve() {
+        let func = |x: f64| x.sin() + x.powi(3);
+        let deriv = |x: f64| x.cos() + 3.0*x*x;
+        let expected = inv_cubic_solve(-1.0, 0.5, f64::EPSILON.sqrt(), DEFAULT_MAX_ITERS, func, deriv).unwrap();
+        assert_eq!(Solver::new().solve(-1.0, 0.5, func, deriv).unwrap(), expected);
+        assert_eq!(Solver::default(), Solver::new().tol(f64::EPSILON.sqrt()).max_iters(1000).bisection_freq(5));
+    }
+
+    #[test]
+    fn higher_bisection_freq_takes_fewer_iterations() {
+        let func = |x: f64| x.sin() + x.powi(3);
+        let deriv = |x: f64| x.cos() + 3.0*x*x;
+        let iterations = |freq| {
+            let (root, stats) = Solver::new().tol(1e-14).bisection_freq(freq).solve_with_stats(-2.0, 1.0, func, deriv).unwrap();
+            assert!(stats.converged && root.abs() < 1e-14);
+            stats.iterations
+        };
+        assert!(iterations(10) < iterations(5));
+        assert!(iterations(5) < iterations(1));
+        // with the periodic bisection off, the solve still converges
+        assert!(iterations(usize::MAX) <= iterations(5));
+    }
+
+    #[test]
+    fn pole_inside_the_bracket_is_an_error() {
+        let func = |x: f64| 1.0/x;
+        let deriv = |x: f64| -1.0/(x*x);
+        for &(x0, x1) in &[(-1.0, 1.0), (-2.0, 1.0)] {
+            assert_eq!(inv_cubic_solve(x0, x1, 1e-12, 100, func, deriv), Err(SolveError::NonFiniteFunctionValue { x: 0.0 }));
+        }
+        // or at an end of the bracket
+        assert_eq!(inv_cubic_solve(0.0, 1.0, 1e-12, 100, func, deriv), Err(SolveError::NonFiniteFunctionValue { x: 0.0 }));
+    }
+
+    #[test]
+    fn newton_fallback_helps_when_the_cubic_is_rejected() {
+        // the horizontal tangent at 0 makes the inverse cubic unusable until that end moves, while a Newton step from
+        // x = 2 is still good
+        let func = |x: f64| x*x - 2.0;
+        let deriv = |x: f64| 2.0*x;
+        let solver = Solver::new().tol(1e-13);
+        let (cubic_root, cubic) = solver.solve_with_stats(0.0, 2.0, func, deriv).unwrap();
+        let (newton_root, newton) = solver.newton_fallback(true).solve_with_stats(0.0, 2.0, func, deriv).unwrap();
+        assert!((cubic_root - 2f64.sqrt()).abs() < 1e-13 && (newton_root - 2f64.sqrt()).abs() < 1e-13);
+        assert!(newton.iterations < cubic.iterations, "{} vs {}", newton.iterations, cubic.iterations);
+    }
+
+    #[test]
+    fn find_bracket_reaches_a_far_root() {
+        let func = |x: f64| x - 1000.0;
+        let (x0, x1) = find_bracket(func, 0.0, 1.0, 50).unwrap();
+        assert!(x0 < 1000.0 && 1000.0 < x1);
+        assert!(opposite_signs(func(x0), func(x1)));
+
+        let root = Solver::new().solve_from(0.0, |x: f64| x.powi(3) - 1e6, |x| 3.0*x*x).unwrap();
+        assert!((root - 100.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn find_bracket_gives_up_without_a_sign_change() {
+        let evals = Cell::new(0);
+        let func = |x: f64| { evals.set(evals.get() + 1); x*x + 1.0 };
+        assert_eq!(find_bracket(func, 0.0, 1.0, 20), None);
+        // the two starting points, then one more per expansion
+        assert_eq!(evals.get(), 22);
+        assert_eq!(Solver::new().solve_from(0.0, |x: f64| x*x + 1.0, |x| 2.0*x), Err(SolveError::NonBracketingInterval));
+    }
+
+    #[test]
+    fn end_of_the_bracket_that_is_a_root_is_returned() {
+        let func = |x: f64| x*x - 4.0;
+        let (root, stats) = inv_cubic_solve_with_stats(2.0, 5.0, 1e-12, 100, func, |x| 2.0*x).unwrap();
+        assert_eq!(root, 2.0);
+        assert_eq!((stats.iterations, stats.deriv_evals), (0, 0));
+        assert_eq!(inv_cubic_solve(0.0, 2.0, 1e-12, 100, func, |x| 2.0*x), Ok(2.0));
+
+        // a trial point that is an exact root is returned straight away, with nothing left to do
+        let (root, stats) = inv_cubic_solve_with_stats(-1.0, 5.0, 0.0, 100, func, |x| 2.0*x).unwrap();
+        assert_eq!(func(root), 0.0);
+        assert!(stats.converged);
+    }
+
+    #[test]
+    fn false_position_solve_does_not_stall_on_a_convex_function() {
+        let func = |x: f64| x.powi(10) - 1.0;
+        let tol = 1e-10;
+
+        // plain regula falsi never moves the upper end, and creeps up on the root from below
+        let (mut x0, mut x1, mut f0, mut f1) = (0.0, 1.3, func(0.0), func(1.3));
+        let mut naive_evals = 2;
+        while (x1 - x0) >= tol && f0.abs().min(f1.abs()) >= tol {
+            let x = false_position(x0, x1, f0, f1);
+            let f = func(x);
+            naive_evals += 1;
+            if f < 0.0 { x0 = x; f0 = f; } else { x1 = x; f1 = f; }
+        }
+        assert_eq!(x1, 1.3);
+
+        let evals = Cell::new(0);
+        let root = false_position_solve(0.0, 1.3, tol, 100, |x| { evals.set(evals.get() + 1); func(x) }).unwrap();
+        assert!((root - 1.0).abs() < tol);
+        assert!(4*evals.get() < naive_evals, "{} vs {}", evals.get(), naive_evals);
+    }
+
+    #[test]
+    fn linear_function_only_takes_inverse_cubic_steps() {
+        for &(a, b) in &[(3.0, -1.0), (-0.1, 0.7), (1e3, 2.5)] {
+            let func = |x: f64| a*x + b;
+            let (root, history) = Solver::new().tol(1e-14).solve_with_history(-10.0, 10.0, func, |_| a).unwrap();
+            assert!((root + b/a).abs() < 1e-14);
+            assert!(history.iter().skip(1).all(|state| state.step == Some(StepKind::InverseCubic)), "{:?}", history);
+
+            let (_, stats) = Solver::new().tol(1e-14).solve_with_stats(-10.0, 10.0, func, |_| a).unwrap();
+            assert_eq!(stats.last_step, Some(StepKind::InverseCubic));
+        }
+    }
+
+    #[test]
+    fn horizontal_tangent_at_an_end_gives_no_nan_or_inf() {
+        let func = |x: f64| x*x - 1.0;
+        let deriv = |x: f64| 2.0*x;
+        assert_eq!(CubicPredictor.predict(0.0, 3.0, -1.0, 0.0, 8.0, 6.0), None);
+
+        let (root, history) = Solver::new().tol(1e-14).solve_with_history(0.0, 3.0, func, deriv).unwrap();
+        assert!((root - 1.0).abs() < 1e-14);
+        for state in &history {
+            assert!([state.x0, state.x1, state.f0, state.f1].iter().all(|v| v.is_finite()), "{:?}", state);
+        }
+        // the inverse cubic isn't tried until the flat end has moved
+        let flat = history.iter().take_while(|state| state.x0 == 0.0).count();
+        assert!(history[1..flat].iter().all(|state| state.step != Some(StepKind::InverseCubic)), "{:?}", history);
+    }
+
+    #[test]
+    fn solve_for_a_target_inverts_a_cube() {
+        let root = inv_cubic_solve_for(0.0, 5.0, 8.0, 1e-13, 100, |x: f64| x.powi(3), |x| 3.0*x*x).unwrap();
+        assert!((root - 2.0).abs() < 1e-13);
+        // the target shifts the sign test, so the bracket must straddle 8, not 0
+        assert_eq!(inv_cubic_solve_for(3.0, 5.0, 8.0, 1e-13, 100, |x: f64| x.powi(3), |x| 3.0*x*x), Err(SolveError::NonBracketingInterval));
+    }
+
+    #[test]
+    fn final_bracket_contains_the_root() {
+        let func = |x: f64| x*x - 2.0;
+        let tol = 1e-10;
+        // a residual tolerance of zero, so the solve only ends on the width
+        let (x0, x1) = Solver::new().x_tol(tol).f_tol(0.0).solve_bracket(1.0, 2.0, func, |x| 2.0*x).unwrap();
+        assert!(x0 <= 2f64.sqrt() && 2f64.sqrt() <= x1, "{} {}", x0, x1);
+        assert!(x1 - x0 < tol);
+        assert!(opposite_signs(func(x0), func(x1)));
+    }
+
+    #[test]
+    fn halley_steps_take_fewer_iterations() {
+        let func = |x: f64| x.exp() - 2.0;
+        let solver = Solver::new().tol(1e-14);
+        let (root, plain) = solver.solve_with_stats(0.0, 3.0, func, |x| x.exp()).unwrap();
+        let halley = solve_core(&solver, &solver, Start::Bracket(0.0, 3.0), func, |x: f64| x.exp(), Some(|x: f64| x.exp()), CubicPredictor, |_| true).unwrap();
+        assert!((root - 2f64.ln()).abs() < 1e-14 && (halley.root - 2f64.ln()).abs() < 1e-14);
+        assert!(halley.stats.iterations < plain.iterations, "{} vs {}", halley.stats.iterations, plain.iterations);
+        assert_eq!(inv_cubic_solve_halley(0.0, 3.0, 1e-14, 100, func, |x| x.exp(), |x| x.exp()), Ok(halley.root));
+    }
+
+    #[test]
+    fn reversed_bracket_gives_the_same_root() {
+        let func = |x: f64| x.sin() + x.powi(3) - 0.5;
+        let deriv = |x: f64| x.cos() + 3.0*x*x;
+        let (forward, forward_stats) = inv_cubic_solve_with_stats(-1.0, 2.0, 1e-12, 100, func, deriv).unwrap();
+        let (reversed, reversed_stats) = inv_cubic_solve_with_stats(2.0, -1.0, 1e-12, 100, func, deriv).unwrap();
+        assert_eq!(forward, reversed);
+        assert_eq!(forward_stats.iterations, reversed_stats.iterations);
+        assert_eq!(Solver::new().solve_bracket(2.0, -1.0, func, deriv), Solver::new().solve_bracket(-1.0, 2.0, func, deriv));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn solver_settings_round_trip_through_json() {
+        let solver = Solver::new().x_tol(1e-10).f_tol(1e-8).rel_tol(1e-6).max_iters(50).bisection_freq(3)
+                                  .strategy(Strategy::EvalMiserly).newton_fallback(true).stagnation(1e-14, 4)
+                                  .timeout(std::time::Duration::from_millis(250));
+        let json = serde_json::to_string(&solver).unwrap();
+        let loaded: Solver = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, solver);
+
+        let (_, stats) = loaded.solve_with_stats(-1.0, 0.5, |x: f64| x.sin() + x.powi(3), |x| x.cos() + 3.0*x*x).unwrap();
+        let value = serde_json::to_value(stats).unwrap();
+        assert_eq!(value["iterations"], stats.iterations);
+        assert_eq!(value["converged"], true);
+    }
+
+    // func, deriv and the bracket
+    type Problem = (fn(f64) -> f64, fn(f64) -> f64, f64, f64);
+
+    #[test]
+    fn incremental_best_point_matches_a_recomputation() {
+        let problems: [Problem; 3] = [
+            (|x| x.sin() + x.powi(3), |x| x.cos() + 3.0*x*x, -1.0, 0.5),
+            (|x| x.powi(10) - 1.0, |x| 10.0*x.powi(9), 0.0, 1.3),
+            (|x| (1e6*(x - 0.3)).atan(), |x| 1e6/(1.0 + 1e12*(x - 0.3)*(x - 0.3)), 0.0, 1.0)
+        ];
+        for &(func, deriv, x0, x1) in &problems {
+            let mut f_seen = f64::INFINITY;
+            for state in Solver::new().tol(1e-14).iter(x0, x1, func, deriv).unwrap() {
+                let (r0, r1) = (state.f0.abs(), state.f1.abs());
+                assert_eq!(state.f_min(), r0.min(r1));
+                assert_eq!(state.f_max(), r0.max(r1));
+                assert_eq!(state.x_best(), if r0 < r1 || state.f0 == 0.0 { state.x0 } else { state.x1 });
+
+                f_seen = f_seen.min(r0).min(r1);
+                assert_eq!(state.f_best_global(), f_seen);
+                assert_eq!(func(state.x_best_global()).abs(), f_seen);
+            }
+        }
+    }
+
+    #[test]
+    fn damping_helps_near_an_inflection() {
+        // the inflection at 0 sits between the ends and the root at 0.1, so the early cubic steps barely shrink the
+        // bracket
+        let func = |x: f64| x.powi(3) - 1e-3;
+        let deriv = |x: f64| 3.0*x*x;
+        let iterations = |accept_frac| {
+            let (root, stats) = Solver::new().tol(1e-13).accept_frac(accept_frac).solve_with_stats(-1.0, 2.0, func, deriv).unwrap();
+            assert!((root - 0.1).abs() < 1e-13);
+            stats.iterations
+        };
+        assert!(iterations(0.5) < iterations(1.0), "{} vs {}", iterations(0.5), iterations(1.0));
+    }
+
+    #[test]
+    fn stepping_by_hand_matches_the_solver() {
+        let func = |x: f64| x.sin() + x.powi(3) - 0.5;
+        let deriv = |x: f64| x.cos() + 3.0*x*x;
+        let cfg = Solver::new().tol(1e-13);
+
+        let mut state = cfg.initial_state(-1.0, 2.0, &func, &deriv).unwrap();
+        let mut history = vec![state];
+        while state.f_min() != 0.0 && !cfg.is_converged(state.x0, state.x1, state.f0, state.f1) {
+            state = solve_step(&state, &cfg, &func, &deriv).unwrap();
+            history.push(state);
+        }
+
+        let (root, stats) = inv_cubic_solve_with_stats(-1.0, 2.0, 1e-13, 1000, func, deriv).unwrap();
+        assert_eq!(state.root(), root);
+        assert_eq!(state.n_iters - 1, stats.iterations);
+        assert_eq!(history, cfg.solve_with_history(-1.0, 2.0, func, deriv).unwrap().1);
+    }
+
+    #[test]
+    fn illinois_step_beats_bisecting_a_stuck_false_position() {
+        let func = |x: f64| (4.0*x).exp() - 3.0;
+        let tol = 1e-12;
+
+        // false position, bisecting after the same end has moved twice in a row, as before the Illinois step
+        let (mut x0, mut x1, mut f0, mut f1) = (0.0, 2.0, func(0.0), func(2.0));
+        let (mut iterations, mut last_moved, mut bisect) = (0, None, false);
+        while (x1 - x0) >= tol && f0.abs().min(f1.abs()) >= tol {
+            let x = if bisect { (x0 + x1)/2.0 } else { false_position(x0, x1, f0, f1) };
+            let f = func(x);
+            let moved = f < 0.0;
+            if moved { x0 = x; f0 = f; } else { x1 = x; f1 = f; }
+            bisect = !bisect && last_moved == Some(moved);
+            last_moved = if bisect { None } else { Some(moved) };
+            iterations += 1;
+        }
+
+        // only the Illinois step guards false position once the periodic bisection is off
+        let solver = Solver::new().tol(tol).cubic(false).bisection_freq(0);
+        let (root, history) = solver.solve_with_history(0.0, 2.0, func, |_| f64::NAN).unwrap();
+        assert!((root - 3f64.ln()/4.0).abs() < tol);
+        assert!(history.iter().any(|state| state.illinois));
+        assert!(history.len() - 1 < iterations, "{} vs {}", history.len() - 1, iterations);
+    }
+
+    #[test]
+    fn iterations_stay_within_the_guaranteed_bound() {
+        let problems: [Problem; 4] = [
+            (jump, |_| 1.0, -1.0, 2.0),
+            (|x| x.powi(10) - 1.0, |x| 10.0*x.powi(9), 0.0, 1.3),
+            (|x| (1e6*(x - 0.3)).atan(), |x| 1e6/(1.0 + 1e12*(x - 0.3)*(x - 0.3)), 0.0, 1.0),
+            // a derivative of the wrong sign, so the inverse cubic always points the wrong way
+            (|x| x.powi(3) - 0.2, |x| -3.0*x*x, -3.0, 5.0)
+        ];
+        for &(func, deriv, x0, x1) in &problems {
+            for &tol in &[1e-3, 1e-6, 1e-9, 1e-12] {
+                for &freq in &[2, 5, 10] {
+                    let solver = Solver::new().x_tol(tol).f_tol(0.0).bisection_freq(freq).max_iters(10_000);
+                    let (_, stats) = solver.solve_with_stats(x0, x1, func, deriv).unwrap();
+                    let bound = freq*((x1 - x0)/tol).log2().ceil() as usize;
+                    assert!(stats.iterations <= bound, "{} iterations for a bound of {}", stats.iterations, bound);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn zero_width_bracket_gives_no_log10_width() {
+        let mut infos = Vec::new();
+        let root = Solver::new().solve_with_callback(2.0, 2.0, |x: f64| x - 2.0, |_| 1.0, |info| infos.push(*info)).unwrap();
+        assert_eq!(root, 2.0);
+        assert_eq!(infos.len(), 1);
+        assert_eq!(infos[0].dx, 0.0);
+        assert_eq!(infos[0].log10_width(), None);
+        assert!(format_iteration(&infos[0], 3).contains("dx = 0.00e0"));
+
+        let (_, history) = Solver::new().solve_with_history(2.0, 2.0, |x: f64| x - 2.0, |_| 1.0).unwrap();
+        let mut csv = Vec::new();
+        write_history_csv(&history, &mut csv).unwrap();
+        assert!(String::from_utf8(csv).unwrap().lines().nth(1).unwrap().ends_with(','));
+    }
+
+    #[test]
+    fn step_landing_on_an_end_is_followed_by_a_bisection() {
+        // the root is 1 + 1e-20, which rounds to the end at 1, so false position keeps landing there. Without the
+        // bisection that follows, this used up all the iterations.
+        let func = |x: f64| (x - 1.0)*1e10 - 1e-10;
+        for solver in &[Solver::new().strategy(Strategy::EvalMiserly), Solver::new().bisection_freq(usize::MAX)] {
+            let mut iterations = 0;
+            let result = solver.tol(0.0).solve_with_callback(1.0, 2.0, func, |_| 1e10, |_| iterations += 1);
+            assert_eq!(result, Err(SolveError::MachinePrecisionReached { best: 1.0 }));
+            // halving [1, 2] down to adjacent floats takes 52 bisections, at worst every third step
+            assert!(iterations <= 3*53, "{} iterations", iterations);
+        }
+    }
+
+    #[test]
+    fn history_has_a_state_per_iteration() {
+        let func = |x: f64| x.sin() + x.powi(3) - 0.5;
+        let deriv = |x: f64| x.cos() + 3.0*x*x;
+        let solver = Solver::new().tol(1e-12);
+        let (root, history) = solver.solve_with_history(-1.0, 2.0, func, deriv).unwrap();
+        let (_, stats) = solver.solve_with_stats(-1.0, 2.0, func, deriv).unwrap();
+
+        // the initial bracket, then one state per iteration
+        assert_eq!(history.len(), stats.iterations + 1);
+        assert!(history.iter().enumerate().all(|(i, state)| state.n_iters == i + 1));
+        assert_eq!(history[0].step, None);
+
+        let last = history.last().unwrap();
+        assert!(solver.is_converged(last.x0, last.x1, last.f0, last.f1));
+        assert!(history[..history.len() - 1].iter().all(|s| !solver.is_converged(s.x0, s.x1, s.f0, s.f1)));
+        assert_eq!(last.root(), root);
+    }
+
+    #[test]
+    fn equal_ends_are_an_error_unless_a_root() {
+        let evals = Cell::new(0);
+        let func = |x: f64| { evals.set(evals.get() + 1); x*x - 2.0 };
+        assert_eq!(inv_cubic_solve(2.0, 2.0, 1e-12, 100, func, |x| 2.0*x), Err(SolveError::NonBracketingInterval));
+        // the single point is only evaluated once
+        assert_eq!(evals.get(), 1);
+        assert_eq!(inv_cubic_solve(2.0, 2.0, 1e-12, 100, |x: f64| x*x - 4.0, |x| 2.0*x), Ok(2.0));
+    }
+
+    #[test]
+    fn inverse_quadratic_takes_over_when_the_cubic_is_rejected() {
+        // with no usable derivative the cubic predictor rejects every bracket
+        let func = |x: f64| x.exp() - 2.0;
+        let deriv = |_| f64::NAN;
+        let (root, history) = Solver::new().tol(1e-13).solve_with_history(0.0, 2.0, func, deriv).unwrap();
+        assert!((root - 2f64.ln()).abs() < 1e-13);
+        assert!(history.iter().all(|state| state.step != Some(StepKind::InverseCubic)));
+        assert!(history.iter().any(|state| state.step == Some(StepKind::InverseQuadratic)));
+
+        // and beats false position and bisection alone
+        let (_, fallback) = Solver::new().tol(1e-13).cubic(false).solve_with_history(0.0, 2.0, func, deriv).unwrap();
+        assert!(history.len() < fallback.len(), "{} vs {}", history.len(), fallback.len());
+    }
+
+    #[test]
+    fn wrong_derivative_is_caught() {
+        let func = |x: f64| x.sin() + x.powi(3) - 0.5;
+        // the derivative of x^3 - sin(x)
+        let wrong = |x: f64| 3.0*x*x - x.cos();
+        let result = Solver::new().check_derivatives(Some(1e-6)).solve(0.1, 1.0, func, wrong);
+        match result {
+            Err(SolveError::DerivativeMismatch { x, analytic, numeric }) => {
+                assert_eq!(x, 0.1);
+                assert_eq!(analytic, wrong(0.1));
+                assert!((numeric - (0.1f64.cos() + 0.03)).abs() < 1e-8);
+            },
+            _ => panic!("{:?}", result)
+        }
+
+        // the right derivative passes
+        let root = Solver::new().check_derivatives(Some(1e-6)).solve(0.1, 1.0, func, |x| x.cos() + 3.0*x*x).unwrap();
+        assert!(func(root).abs() < 1e-7);
+    }
+
+    #[test]
+    fn combined_closure_matches_separate_functions() {
+        let func = |x: f64| x.exp() - 3.0*x;
+        let deriv = |x: f64| x.exp() - 3.0;
+        let (separate, stats) = Solver::new().tol(1e-13).solve_with_stats(1.0, 2.0, func, deriv).unwrap();
+
+        let calls = Cell::new(0);
+        let combined = Solver::new().tol(1e-13).solve_combined(1.0, 2.0, |x| {
+            calls.set(calls.get() + 1);
+            let e = x.exp();
+            (e - 3.0*x, e - 3.0)
+        }).unwrap();
+        assert_eq!(combined, separate);
+        // once per point, where the separate path calls func and deriv there
+        assert_eq!(calls.get(), stats.func_evals);
+        assert_eq!(inv_cubic_solve_combined(1.0, 2.0, 1e-13, 100, |x| (func(x), deriv(x))), Ok(separate));
+    }
+
+    #[test]
+    fn derivatives_spanning_hundreds_of_orders_of_magnitude() {
+        // f' is 1 at the lower end and 1e304 at the upper one
+        let func = |x: f64| x.exp() - 1e100;
+        let root = Solver::new().rel_tol(1e-14).solve(0.0, 700.0, func, |x| x.exp()).unwrap();
+        assert!((root - 100.0*10f64.ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn boxed_closures_are_solved_dynamically() {
+        type Boxed = Box<dyn Fn(f64) -> f64>;
+        let problems: Vec<(Boxed, Boxed, f64)> = (1..=4).map(|k| {
+            let k = k as f64;
+            (Box::new(move |x: f64| x*x - k) as Boxed, Box::new(|x: f64| 2.0*x) as Boxed, k.sqrt())
+        }).collect();
+        for (func, deriv, expected) in &problems {
+            let root = inv_cubic_solve_dyn(0.0, 3.0, 1e-13, 100, func.as_ref(), deriv.as_ref()).unwrap();
+            assert!((root - expected).abs() < 1e-13, "{} vs {}", root, expected);
+        }
+    }
+
+    #[test]
+    fn stagnation_stops_a_noisy_solve() {
+        // within about 1e-6 of 0.3 the noise swamps the slope, with many sign changes, so the root is only known to
+        // about 1e-6 however narrow the bracket gets
+        let func = |x: f64| (x - 0.3) + 1e-6*(1e7*x).sin();
+        let deriv = |x: f64| 1.0 + 10.0*(1e7*x).cos();
+
+        let mut plain = 0;
+        let result = Solver::new().tol(0.0).solve_with_callback(0.0, 1.0, func, deriv, |_| plain += 1);
+        assert!(matches!(result, Err(SolveError::MachinePrecisionReached { .. })), "{:?}", result);
+
+        let mut stagnating = 0;
+        let result = Solver::new().tol(0.0).stagnation(1e-9, 4).solve_with_callback(0.0, 1.0, func, deriv, |_| stagnating += 1);
+        match result {
+            Err(SolveError::Stagnated { best }) => assert!((best - 0.3).abs() < 3e-6, "{}", best),
+            _ => panic!("{:?}", result)
+        }
+        assert!(stagnating < plain, "{} vs {}", stagnating, plain);
+    }
+
+    // scale*(x - roots[0])*(x - roots[1])*..., and its derivative
+    fn polynomial(roots: &[f64], scale: f64, x: f64) -> (f64, f64) {
+        let f = roots.iter().fold(scale, |p, r| p*(x - r));
+        let df = (0..roots.len()).map(|i| {
+            roots.iter().enumerate().filter(|&(j, _)| j != i).fold(scale, |p, (_, r)| p*(x - r))
+        }).sum();
+        (f, df)
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn random_polynomial_converges_to_the_bracketed_root(mut roots in proptest::collection::vec(-10.0f64..10.0, 1..6),
+                                                              exponent in -250i32..250, negative: bool, pick: proptest::sample::Index) {
+            roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            proptest::prop_assume!(roots.windows(2).all(|w| w[1] - w[0] > 1e-3));
+            // values anywhere from about 1e-300 to 1e256, so a product of two of them would underflow or overflow
+            let scale = if negative { -10f64.powi(exponent) } else { 10f64.powi(exponent) };
+
+            // a bracket around roots[k] alone, halfway to its neighbours
+            let k = pick.index(roots.len());
+            let lo = if k == 0 { roots[0] - 1.0 } else { (roots[k - 1] + roots[k])/2.0 };
+            let hi = if k == roots.len() - 1 { roots[k] + 1.0 } else { (roots[k] + roots[k + 1])/2.0 };
+
+            let func = |x: f64| polynomial(&roots, scale, x).0;
+            let deriv = |x: f64| polynomial(&roots, scale, x).1;
+            let (root, history) = Solver::new().x_tol(1e-10).f_tol(0.0).solve_with_history(lo, hi, func, deriv).unwrap();
+            proptest::prop_assert!((root - roots[k]).abs() < 1e-9, "{} vs {}", root, roots[k]);
+            for state in &history {
+                proptest::prop_assert!(state.f0 == 0.0 || state.f1 == 0.0 || opposite_signs(state.f0, state.f1), "{:?}", state);
+                proptest::prop_assert!(state.x0 - 1e-9 <= roots[k] && roots[k] <= state.x1 + 1e-9, "{:?}", state);
+            }
+        }
+    }
+
+    #[test]
+    fn solve_monotone_reaches_a_far_root() {
+        let evals = Cell::new(0);
+        let func = |x: f64| { evals.set(evals.get() + 1); x.sinh() - 1e8 };
+        let root = solve_monotone(0.0, 1e-12, func, |x| x.cosh()).unwrap();
+        assert!((root - 1e8f64.asinh()).abs() < 1e-12);
+
+        // only ever searching one way takes fewer evaluations than find_bracket, which searches both
+        let monotone = evals.replace(0);
+        Solver::new().tol(1e-12).solve_from(0.0, func, |x| x.cosh()).unwrap();
+        assert!(monotone < evals.get(), "{} vs {}", monotone, evals.get());
+    }
+
+    #[test]
+    fn solve_monotone_backs_off_where_the_function_overflows() {
+        // the Newton step from 0 is 1e6, where exp overflows
+        let root = solve_monotone(0.0, 1e-12, |x: f64| x.exp() - 1e6, |x| x.exp()).unwrap();
+        assert!((root - 1e6f64.ln()).abs() < 1e-12);
+
+        // even with a first step of 100, exp overflows a few steps out
+        let root = solve_monotone(0.0, 1e-12, |x: f64| x.exp() - 1e300, |x| x.exp()).unwrap();
+        assert!((root - 1e300f64.ln()).abs() < 1e-9);
+        // and where the sign change is beyond the overflow, the search gives up cleanly
+        assert_eq!(solve_monotone(0.0, 1e-12, |x: f64| x.exp() - f64::MAX, |x| x.exp()), Err(SolveError::NonBracketingInterval));
+    }
+
+    #[test]
+    fn inconsistent_function_gives_an_answer_not_a_panic() {
+        // the sign flips from one call to the next, whatever x is
+        let calls = Cell::new(0);
+        let func = |x: f64| { calls.set(calls.get() + 1); if calls.get() % 2 == 0 { 1.0 + x } else { -1.0 - x } };
+        let result = Solver::new().tol(1e-12).solve_with_stats(0.0, 1.0, func, |_| 1.0);
+        assert!(result.is_ok() || matches!(result, Err(SolveError::MachinePrecisionReached { .. })), "{:?}", result);
+    }
+
+    // a state whose ends no longer bracket a root, as only changing its public fields by hand can make
+    fn broken_state() -> BracketState<f64> {
+        let mut state = Solver::new().initial_state(-1.0, 1.0, &|x: f64| x, &|_| 1.0).unwrap();
+        state.f0 = 1.0;
+        state
+    }
+
+    #[cfg(not(debug_assertions))]
+    #[test]
+    fn broken_bracket_is_an_error_in_release_builds() {
+        let result = solve_step(&broken_state(), &Solver::new(), &|x: f64| x + 2.0, &|_| 1.0);
+        assert_eq!(result, Err(SolveError::NonBracketingInterval));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "bracket invariant violated")]
+    fn broken_bracket_fails_the_debug_assertion() {
+        let _ = solve_step(&broken_state(), &Solver::new(), &|x: f64| x + 2.0, &|_| 1.0);
+    }
+
+    #[test]
+    fn format_iteration_respects_the_digits() {
+        let info = IterationInfo { n_iters: 3, x0: 0.125, x1: 0.15, f_min: 2.44140625e-4, f_max: 3.90625e-3, dx: 0.025, step: Some(StepKind::Bisection) };
+        assert_eq!(format_iteration(&info, 4), "  3 x0 = 1.250e-1 x1 = 1.500e-1 f_min = 2.441e-4 f_max = 3.906e-3 dx = 2.500e-2 step = Bisection");
+        // at least one digit
+        assert_eq!(format_iteration(&info, 0), format_iteration(&info, 1));
+
+        for digits in 1..17 {
+            let line = format_iteration(&info, digits);
+            let numbers: Vec<f64> = line.split(" = ").skip(1).take(5).map(|s| s.split(' ').next().unwrap().parse().unwrap()).collect();
+            for (n, exact) in numbers.iter().zip(&[info.x0, info.x1, info.f_min, info.f_max, info.dx]) {
+                assert!(((n - exact)/exact).abs() <= 0.5*10f64.powi(1 - digits as i32), "{} digits: {} vs {}", digits, n, exact);
+            }
+            let mantissa = line.split(" = ").nth(1).unwrap().split('e').next().unwrap();
+            assert_eq!(mantissa.chars().filter(|c| c.is_ascii_digit()).count(), digits);
+        }
+    }
+
+    #[test]
+    fn integer_root_is_the_nearest_integer() {
+        // the continuous root is about 2.9907
+        assert_eq!(inv_cubic_solve_integer(0.0, 5.0, |x: f64| x.powi(3) - 26.75, |x| 3.0*x*x), Ok(3));
+        // and about 3.37, where 3 still has the smaller residual
+        assert_eq!(inv_cubic_solve_integer(0.0, 5.0, |x: f64| x*x - 11.4, |x| 2.0*x), Ok(3));
+        // a tie at 3.5 goes to the floor
+        assert_eq!(inv_cubic_solve_integer(0.0, 5.0, |x: f64| x - 3.5, |_| 1.0), Ok(3));
+        assert_eq!(inv_cubic_solve_integer(4.0, 5.0, |x: f64| x - 3.5, |_| 1.0), Err(SolveError::NonBracketingInterval));
+    }
+
+    #[test]
+    fn cancelling_stops_the_solve_promptly() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        // the flag is set after a few evaluations, as another thread might
+        let cancel = AtomicBool::new(false);
+        let evals = Cell::new(0);
+        let func = |x: f64| {
+            evals.set(evals.get() + 1);
+            if evals.get() == 5 { cancel.store(true, Ordering::Relaxed); }
+            jump(x)
+        };
+        let result = Solver::new().tol(0.0).solve_cancellable(-1.0, 1.0, func, |_| 1.0, || !cancel.load(Ordering::Relaxed));
+        match result {
+            Err(SolveError::Cancelled { best }) => assert!((-1.0..=1.0).contains(&best)),
+            _ => panic!("{:?}", result)
+        }
+        assert_eq!(evals.get(), 5);
+
+        // from another thread
+        let cancel = AtomicBool::new(false);
+        let result = std::thread::scope(|s| {
+            s.spawn(|| { std::thread::sleep(std::time::Duration::from_millis(20)); cancel.store(true, Ordering::Relaxed) });
+            let slow = |x: f64| { std::thread::sleep(std::time::Duration::from_millis(1)); jump(x) };
+            Solver::new().tol(0.0).max_iters(usize::MAX).solve_cancellable(-1.0, 1.0, slow, |_| 1.0, || !cancel.load(Ordering::Relaxed))
+        });
+        assert!(matches!(result, Err(SolveError::Cancelled { .. }) | Err(SolveError::MachinePrecisionReached { .. })), "{:?}", result);
+    }
+
+    #[test]
+    fn inverse_cubic_converges_superlinearly() {
+        let func = |x: f64| x.exp() - 2.0;
+        let root = 2f64.ln();
+        let (_, history) = Solver::new().tol(1e-15).solve_with_history(0.0, 3.0, func, |x| x.exp()).unwrap();
+        // leaving out the last error, which is down to the rounding of the root
+        let errors: Vec<f64> = history.iter().map(|state| state.x_best() - root).filter(|e| e.abs() > 1e-14).collect();
+        let orders: Vec<f64> = convergence_order(&errors).collect();
+        assert!(orders.len() >= 3, "{:?}", errors);
+        assert!(orders.iter().all(|&order| order > 1.5), "{:?}", orders);
+    }
+
+    #[test]
+    fn scratch_is_reused_without_allocating() {
+        let func = |x: f64| x.sin() + x.powi(3) - 0.5;
+        let deriv = |x: f64| x.cos() + 3.0*x*x;
+        let solver = Solver::new().tol(1e-12);
+        let mut scratch = SolveScratch::new();
+        solver.solve_with_scratch(-1.0, 2.0, func, deriv, &mut scratch).unwrap();
+        let (ptr, capacity) = (scratch.history.as_ptr(), scratch.history.capacity());
+
+        // shifted problems take no more iterations than fit in the storage from the first solve
+        for k in 0..100 {
+            let shift = k as f64/100.0;
+            let root = solver.solve_with_scratch(-1.0, 2.0, |x| func(x) - shift, deriv, &mut scratch).unwrap();
+            assert_eq!(scratch.history().last().unwrap().root(), root);
+            assert_eq!(scratch.history.as_ptr(), ptr);
+            assert_eq!(scratch.history.capacity(), capacity);
+        }
+    }
+
+    #[test]
+    fn relative_residual_ignores_the_scale_of_func() {
+        let solve = |solver: Solver, scale: f64| {
+            solver.solve_with_stats(1.0, 2.0, |x: f64| scale*(x.powi(3) - 2.0), |x| scale*3.0*x*x).unwrap()
+        };
+        let relative = Solver::new().x_tol(0.0).f_tol(0.0).rel_residual(1e-10);
+        let (root, stats) = solve(relative, 1.0);
+        assert!((root - 2f64.cbrt()).abs() < 1e-9);
+        for &scale in &[1e-8, 1e8] {
+            let (scaled_root, scaled_stats) = solve(relative, scale);
+            assert!((scaled_root - root).abs() < 1e-15, "{} vs {}", scaled_root, root);
+            assert_eq!(scaled_stats.iterations, stats.iterations);
+        }
+
+        // whereas an absolute residual tolerance stops far too early on the small scale, and late on the large one
+        let absolute = Solver::new().x_tol(0.0).f_tol(1e-10);
+        assert!(solve(absolute, 1e-8).0 != solve(absolute, 1e8).0);
+        assert!(solve(absolute, 1e-8).1.iterations < solve(absolute, 1e8).1.iterations);
+    }
+
+    #[test]
+    fn exact_root_at_a_trial_point_is_reported_with_zero_residual() {
+        // the first false position step lands exactly on the root at 1
+        let func = |x: f64| 2.0*x - 2.0;
+        let (root, history) = Solver::new().cubic(false).tol(0.0).solve_with_history(0.0, 4.0, func, |_| 2.0).unwrap();
+        assert_eq!(root, 1.0);
+        let last = history.last().unwrap();
+        assert_eq!(last.f_min(), 0.0);
+        // the tie goes to [x0, x_new], so the root is the upper end, and the solve stops there
+        assert_eq!((last.x0, last.x1), (0.0, 1.0));
+        assert_eq!(history.len(), 2);
+    }
+
+    #[test]
+    fn linear_predictor_still_converges() {
+        // the secant through the ends, which ignores the derivatives
+        struct Linear;
+        impl Predictor<f64> for Linear {
+            fn predict(&self, x0: f64, x1: f64, f0: f64, _df0: f64, f1: f64, _df1: f64) -> Option<f64> {
+                Some(x0 - f0*(x1 - x0)/(f1 - f0))
+            }
+        }
+        // and one that rejects every bracket, so only the fallbacks are left
+        struct Never;
+        impl Predictor<f64> for Never {
+            fn predict(&self, _: f64, _: f64, _: f64, _: f64, _: f64, _: f64) -> Option<f64> {
+                None
+            }
+        }
+
+        let func = |x: f64| x.sin() + x.powi(3) - 0.5;
+        let deriv = |x: f64| x.cos() + 3.0*x*x;
+        let expected = Solver::new().tol(1e-12).solve(-1.0, 2.0, func, deriv).unwrap();
+        let linear = Solver::new().tol(1e-12).solve_with_predictor(-1.0, 2.0, func, deriv, Linear).unwrap();
+        let never = Solver::new().tol(1e-12).solve_with_predictor(-1.0, 2.0, func, deriv, Never).unwrap();
+        assert!((linear - expected).abs() < 1e-12 && (never - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn summary_log_has_the_root_and_no_iterations() {
+        let func = |x: f64| x.sin() + x.powi(3) - 0.5;
+        let deriv = |x: f64| x.cos() + 3.0*x*x;
+        let log = |verbosity| {
+            let mut out = Vec::new();
+            let root = Solver::new().tol(1e-12).verbosity(verbosity).solve_logged(0.0, 1.0, func, deriv, &mut out).unwrap();
+            (root, String::from_utf8(out).unwrap())
+        };
+
+        let (root, summary) = log(Verbosity::Summary);
+        assert_eq!(summary.lines().count(), 1);
+        assert!(summary.starts_with(&format!("root = {:e} ", root)), "{}", summary);
+        assert!(!summary.contains("step ="));
+
+        let (_, trace) = log(Verbosity::Trace);
+        let (_, stats) = Solver::new().tol(1e-12).solve_with_stats(0.0, 1.0, func, deriv).unwrap();
+        assert_eq!(trace.lines().filter(|line| line.contains("step =")).count(), stats.iterations + 1);
+        assert!(trace.ends_with(&summary));
+
+        assert_eq!(log(Verbosity::Silent).1, "");
+    }
+
+    #[test]
+    fn root_above_a_finite_lower_bound_with_an_infinite_upper_end() {
+        let solver = Solver::new().tol(1e-9);
+        let root = solver.solve_unbounded(1.0, f64::INFINITY, |x| x - 1000.0, |_| 1.0).unwrap();
+        assert!((root - 1000.0).abs() < 1e-6, "{}", root);
+        let root = solver.solve_unbounded(f64::NEG_INFINITY, 0.0, |x| x.exp() - 0.5, |x| x.exp()).unwrap();
+        assert!((root - 0.5f64.ln()).abs() < 1e-9, "{}", root);
+        let root = solver.solve_unbounded(f64::NEG_INFINITY, f64::INFINITY, |x| x.atan() - 1.0, |x| 1.0/(1.0 + x*x)).unwrap();
+        assert!((root - 1f64.tan()).abs() < 1e-9, "{}", root);
+    }
+
+    #[test]
+    fn unbounded_errors_are_in_terms_of_x() {
+        let err = Solver::new().max_iters(2).tol(1e-15).solve_unbounded(1.0, f64::INFINITY, |x| x - 1000.0, |_| 1.0).unwrap_err();
+        match err {
+            SolveError::DidNotConverge { best } => assert!(best > 1.0 && best.is_finite(), "{}", best),
+            e => panic!("expected DidNotConverge, got {:?}", e)
+        }
+    }
+
+    #[test]
+    fn best_point_dropped_from_the_bracket_is_still_returned() {
+        // not monotone, so a step can replace the best end with a point that has a larger residual
+        let func = |x: f64| x - 0.3 + 3.0*(33.0*x).sin()/33.0;
+        let deriv = |x: f64| 1.0 + 3.0*(33.0*x).cos();
+        let solver = Solver::new().tol(1e-3);
+        let history = solver.solve_with_history(-2.0, 3.0, func, deriv).unwrap().1;
+
+        // after 7 iterations the residual at 0.282 from iteration 4 is still smaller than at either end
+        let state = history[7];
+        assert!(state.f_best_global() < state.f_min());
+        assert!(state.x_best_global() < state.x0.min(state.x1));
+        let (root, stats) = solver.max_iters(7).solve_with_stats(-2.0, 3.0, func, deriv).unwrap();
+        assert!(!stats.converged);
+        assert_eq!(root, state.x_best_global());
+        assert_eq!(func(root).abs(), state.f_best_global());
+    }
+
+    #[test]
+    fn without_the_cubic_steps_the_solve_still_converges() {
+        let func = |x: f64| x.sin() + x.powi(3) - 0.5;
+        let deriv_evals = Cell::new(0);
+        let deriv = |x: f64| { deriv_evals.set(deriv_evals.get() + 1); x.cos() + 3.0*x*x };
+        let (cubic_root, cubic_stats) = Solver::new().tol(1e-12).solve_with_stats(-1.0, 2.0, func, |x| x.cos() + 3.0*x*x).unwrap();
+        let (root, stats) = Solver::new().tol(1e-12).cubic(false).solve_with_stats(-1.0, 2.0, func, deriv).unwrap();
+        assert!(stats.converged);
+        assert!((root - cubic_root).abs() < 1e-12, "{} vs {}", root, cubic_root);
+        assert!(stats.iterations > cubic_stats.iterations, "{} vs {}", stats.iterations, cubic_stats.iterations);
+        assert_eq!(deriv_evals.get(), 0);
+    }
+
+    #[test]
+    fn turning_point_in_the_bracket_is_flagged() {
+        // x^2 - 1 has its minimum at 0, inside [-0.5, 2], so the slopes at the ends are -1 and 4
+        let func = |x: f64| x*x - 1.0;
+        let deriv = |x: f64| 2.0*x;
+        let cfg = Solver::new();
+        let state = cfg.initial_state(-0.5, 2.0, &func, &deriv).unwrap();
+        assert!(state.derivative_signs_differ());
+        let state = cfg.initial_state(0.5, 2.0, &func, &deriv).unwrap();
+        assert!(!state.derivative_signs_differ());
+
+        // the solve still converges
+        let root = cfg.solve(-0.5, 2.0, func, deriv).unwrap();
+        assert!((root - 1.0).abs() < 1e-9, "{}", root);
+    }
+
+    #[test]
+    fn func_eval_budget_is_respected_exactly() {
+        let (_, stats) = Solver::new().tol(1e-15).solve_with_stats(-1.0, 2.0, |x: f64| x.sin() + x.powi(3) - 0.5, |x| x.cos() + 3.0*x*x).unwrap();
+        for budget in 2..stats.func_evals {
+            let evals = Cell::new(0);
+            let func = |x: f64| { evals.set(evals.get() + 1); x.sin() + x.powi(3) - 0.5 };
+            let result = Solver::new().tol(1e-15).func_eval_budget(budget).solve(-1.0, 2.0, func, |x| x.cos() + 3.0*x*x);
+            assert!(matches!(result, Err(SolveError::BudgetExhausted { .. })), "{:?}", result);
+            assert_eq!(evals.get(), budget);
+        }
+
+        // and with the derivative checks, which evaluate func twice more per check
+        let evals = Cell::new(0);
+        let func = |x: f64| { evals.set(evals.get() + 1); x.sin() + x.powi(3) - 0.5 };
+        let result = Solver::new().tol(1e-15).check_derivatives(Some(1e-4)).func_eval_budget(9).solve(-1.0, 2.0, func, |x| x.cos() + 3.0*x*x);
+        assert!(matches!(result, Err(SolveError::BudgetExhausted { .. })), "{:?}", result);
+        assert!(evals.get() <= 9, "{}", evals.get());
+    }
+
+    #[test]
+    fn perturbation_moves_steps_off_the_end_they_keep_landing_on() {
+        // as in step_landing_on_an_end_is_followed_by_a_bisection, false position keeps landing on the end at 1
+        let func = |x: f64| (x - 1.0)*1e10 - 1e-10;
+        let iterations = |solver: Solver| {
+            let mut iterations = 0;
+            let result = solver.tol(0.0).solve_with_callback(1.0, 2.0, func, |_| 1e10, |_| iterations += 1);
+            assert_eq!(result, Err(SolveError::MachinePrecisionReached { best: 1.0 }));
+            iterations
+        };
+        let plain = iterations(Solver::new().strategy(Strategy::EvalMiserly));
+        let perturbed = iterations(Solver::new().strategy(Strategy::EvalMiserly).perturbation(0.01));
+        assert!(perturbed < plain/4, "{} vs {}", perturbed, plain);
+    }
+
+    #[test]
+    fn perturbation_breaks_the_cycle_of_a_symmetric_function() {
+        // x^5 is odd about its root, so on a bracket symmetric about the root the first cubic step lands within rounding
+        // of it, and the later ones keep landing on that same end while the other one closes in
+        let solve = |solver: Solver| {
+            let evaluated = core::cell::RefCell::new(Vec::new());
+            let mut iterations = 0;
+            let root = solver.tol(0.0).solve_with_callback(-1.0, 1.0, |x: f64| { evaluated.borrow_mut().push(x); x.powi(5) },
+                                                           |x| 5.0*x.powi(4), |_| iterations += 1).unwrap();
+            assert!(root.abs() < 1e-60, "{}", root);
+            let evaluated = evaluated.into_inner();
+            let repeats = (0..evaluated.len()).filter(|&i| evaluated[..i].contains(&evaluated[i])).count();
+            (iterations, repeats)
+        };
+        let (plain, plain_repeats) = solve(Solver::new());
+        let (perturbed, perturbed_repeats) = solve(Solver::new().perturbation(0.01));
+        assert!(plain_repeats > 0);
+        assert_eq!(perturbed_repeats, 0);
+        assert!(perturbed < plain, "{} vs {}", perturbed, plain);
+    }
+
+    #[test]
+    fn derivative_at_the_root_is_the_exact_one() {
+        // the root of x^3 - 8 is 2, where the derivative is 12
+        let (root, df) = Solver::new().tol(1e-12).solve_with_derivative(0.0, 5.0, |x: f64| x.powi(3) - 8.0, |x| 3.0*x*x).unwrap();
+        assert!((root - 2.0).abs() < 1e-12, "{}", root);
+        assert_eq!(df, 3.0*root*root);
+        assert!((df - 12.0).abs() < 1e-10, "{}", df);
+
+        // a triple root is ill conditioned, the tiny derivative there says that the residual is already within the
+        // tolerance some way from it
+        let (root, df) = Solver::new().tol(1e-6).solve_with_derivative(0.0, 3.0, |x: f64| (x - 1.0).powi(3), |x| 3.0*(x - 1.0).powi(2)).unwrap();
+        assert!((root - 1.0).abs() < 1e-2, "{}", root);
+        assert_eq!(df, 3.0*(root - 1.0).powi(2));
+        assert!(df < 1e-3, "{}", df);
+    }
+
+    #[test]
+    fn precomputed_ends_are_not_evaluated_again() {
+        let func_evals = Cell::new(0);
+        let deriv_evals = Cell::new(0);
+        let func = |x: f64| { func_evals.set(func_evals.get() + 1); x.sin() + x.powi(3) - 0.5 };
+        let deriv = |x: f64| { deriv_evals.set(deriv_evals.get() + 1); x.cos() + 3.0*x*x };
+        let (_, stats) = Solver::new().tol(1e-12).solve_with_stats(-1.0, 2.0, func, deriv).unwrap();
+        let (func_total, deriv_total) = (func_evals.replace(0), deriv_evals.replace(0));
+        assert_eq!((func_total, deriv_total), (stats.func_evals, stats.deriv_evals));
+
+        let (x0, x1) = (-1.0f64, 2.0f64);
+        let end0 = (x0, x0.sin() + x0.powi(3) - 0.5, x0.cos() + 3.0*x0*x0);
+        let end1 = (x1, x1.sin() + x1.powi(3) - 0.5, x1.cos() + 3.0*x1*x1);
+        let root = Solver::new().tol(1e-12).solve_precomputed(end0, end1, func, deriv).unwrap();
+        assert_eq!(root, inv_cubic_solve(x0, x1, 1e-12, 1000, |x: f64| x.sin() + x.powi(3) - 0.5, |x| x.cos() + 3.0*x*x).unwrap());
+        assert_eq!((func_evals.get(), deriv_evals.get()), (func_total - 2, deriv_total - 2));
+
+        assert_eq!(Solver::new().solve_precomputed((0.0, 1.0, 1.0), (1.0, 2.0, 1.0), func, deriv), Err(SolveError::NonBracketingInterval));
+    }
+
+    #[test]
+    fn zero_tolerance_stops_at_adjacent_floats() {
+        let func = |x: f64| x*x - 2.0;
+        let deriv = |x: f64| 2.0*x;
+        let mut iterations = 0;
+        let result = Solver::new().tol(0.0).solve_with_callback(1.0, 2.0, func, deriv, |_| iterations += 1);
+        let best = match result {
+            Err(SolveError::MachinePrecisionReached { best }) => best,
+            result => panic!("expected MachinePrecisionReached, got {:?}", result)
+        };
+        assert!((best - 2f64.sqrt()).abs() <= f64::EPSILON*2f64.sqrt(), "{}", best);
+        assert!(iterations < 100, "{} iterations", iterations);
+
+        // stepping by hand, the bracket shrinks to a pair of adjacent floats and no further
+        let cfg = Solver::new().tol(0.0);
+        let mut state = cfg.initial_state(1.0, 2.0, &func, &deriv).unwrap();
+        while !state.at_machine_precision() {
+            state = solve_step(&state, &cfg, &func, &deriv).unwrap();
+        }
+        assert_eq!(state.x1.to_bits() - state.x0.to_bits(), 1);
+        assert!(state.x0 == best || state.x1 == best);
+    }
+
+    #[test]
+    fn verified_enclosure_contains_the_root_and_is_within_tol() {
+        let tol = 1e-10;
+        let (lo, hi) = Solver::new().tol(tol).solve_verified(0.0, 2.0, |x: f64| x.exp() - 3.0, |x| x.exp()).unwrap();
+        let root = 3f64.ln();
+        assert!(lo <= root && root <= hi, "[{}, {}]", lo, hi);
+        assert!(hi - lo < tol, "{}", hi - lo);
+        assert!(lo.exp() - 3.0 <= 0.0 && hi.exp() - 3.0 >= 0.0);
+
+        // the residual tolerance doesn't end it early
+        let (lo, hi) = Solver::new().tol(tol).f_tol(1.0).solve_verified(0.0, 2.0, |x: f64| x.exp() - 3.0, |x| x.exp()).unwrap();
+        assert!(lo <= root && root <= hi && hi - lo < tol, "[{}, {}]", lo, hi);
+    }
+
+    #[test]
+    fn hook_keeps_steps_out_of_a_forbidden_region() {
+        let func = |x: f64| x.sin() + x.powi(3) - 0.5;
+        let deriv = |x: f64| x.cos() + 3.0*x*x;
+        let evaluated = core::cell::RefCell::new(Vec::new());
+        let hooked = Cell::new(0);
+        let forbidden = |x: f64| (0.0..0.4).contains(&x);
+        let root = Solver::new().tol(1e-12).solve_with_hook(-1.0, 2.0, |x| { evaluated.borrow_mut().push(x); func(x) }, deriv, |x, _| {
+            hooked.set(hooked.get() + 1);
+            if forbidden(x) { None } else { Some(x) }
+        }).unwrap();
+        assert!((root - Solver::new().tol(1e-12).solve(-1.0, 2.0, func, deriv).unwrap()).abs() < 1e-12);
+        assert!(hooked.get() > 0);
+        // bisections aren't passed to the hook, so only they may land in the region, at the midpoint of two earlier points
+        let evaluated = evaluated.into_inner();
+        for (i, &x) in evaluated.iter().enumerate().filter(|&(_, &x)| forbidden(x)) {
+            let earlier = &evaluated[..i];
+            assert!(earlier.iter().any(|&a| earlier.iter().any(|&b| 0.5*(a + b) == x)), "{} in {:?}", x, evaluated);
+        }
+    }
+
+    #[test]
+    fn false_position_keeps_its_precision_far_from_the_origin() {
+        // the secant through (x0, -0.1) and (x0 + 3, 0.2) crosses zero at x0 + 1, which is exactly representable
+        for &x0 in &[1e15, 1e12, 123456789.0] {
+            let (x1, f0, f1) = (x0 + 3.0, -0.1, 0.2);
+            let products = (x0*f1 - x1*f0)/(f1 - f0);
+            assert_ne!(products, x0 + 1.0);
+            assert_eq!(false_position(x0, x1, f0, f1), x0 + 1.0);
+        }
+
+        // equal values give the midpoint rather than NaN
+        assert_eq!(false_position(1.0, 2.0, 0.5, 0.5), 1.5);
+        assert_eq!(false_position(1.0, 2.0, -f64::MAX, f64::MAX), 1.5);
+    }
+
+    #[test]
+    fn warm_starts_of_a_sweep_take_fewer_evaluations() {
+        let evals = Cell::new(0);
+        let solver = Solver::new().tol(1e-12);
+        let (mut cold, mut warm) = (0, 0);
+        let mut bracket = (0.0, 5.0);
+        for i in 0..=20 {
+            let c = 2.0 + 0.05*f64::from(i);
+            let func = |x: f64| { evals.set(evals.get() + 1); x.exp() - c };
+
+            let root = solver.solve(0.0, 5.0, func, f64::exp).unwrap();
+            cold += evals.replace(0);
+
+            bracket = solver.solve_warm(bracket, 0.05, func, f64::exp).unwrap();
+            warm += evals.replace(0);
+            // the root moves by less than widen each time, so the bracket is never clipped
+            assert!((bracket.0 - (root - 0.05)).abs() < 1e-12 && (bracket.1 - (root + 0.05)).abs() < 1e-12, "{} {:?}", root, bracket);
+        }
+        assert!(warm < cold, "{} vs {}", warm, cold);
+
+        // a root within widen of the end of the widened bracket, here 1.5, is clipped to it
+        let (lo, hi) = solver.solve_warm((0.0, 1.0), 0.5, |x: f64| x.exp() - 4.0, f64::exp).unwrap();
+        assert_eq!(hi, 1.5);
+        assert!((lo - (4f64.ln() - 0.5)).abs() < 1e-12, "{}", lo);
+    }
+
+    #[test]
+    fn outcome_gives_each_termination_reason() {
+        let reason = |solver: Solver, x0: f64, x1: f64, func: fn(f64) -> f64, deriv: fn(f64) -> f64| {
+            solver.solve_outcome(x0, x1, func, deriv).unwrap().reason
+        };
+        // a steep function, whose residual is still large once the bracket is narrow
+        assert_eq!(reason(Solver::new().x_tol(1e-6).f_tol(1e-30), 0.0, 1.0, |x| 1e9*(x - 0.3).powi(3), |x| 3e9*(x - 0.3).powi(2)),
+                   TerminationReason::WidthTolerance);
+        // a flat one, whose residual is small while the bracket is still wide
+        assert_eq!(reason(Solver::new().x_tol(1e-30).f_tol(1e-3), 0.0, 1.0, |x| 1e-6*(x - 0.3), |_| 1e-6),
+                   TerminationReason::ResidualTolerance);
+        assert_eq!(reason(Solver::new().tol(1e-15).max_iters(2), -1.0, 2.0, |x| x.sin() + x.powi(3) - 0.5, |x| x.cos() + 3.0*x*x),
+                   TerminationReason::MaxIterations);
+        assert_eq!(reason(Solver::new().tol(0.0), 1.0, 2.0, |x| x*x - 2.0, |x| 2.0*x), TerminationReason::MachinePrecision);
+        assert_eq!(reason(Solver::new(), -1.0, 2.0, |x| x - 0.5, |_| 1.0), TerminationReason::ExactRoot);
+    }
+
+    #[test]
+    fn eval_miserly_takes_fewer_evaluations_on_a_smooth_function() {
+        let problems: [Problem; 3] = [
+            (|x| x.sin() + x.powi(3) - 0.5, |x| x.cos() + 3.0*x*x, -1.0, 2.0),
+            (|x| x.exp() - 10.0, f64::exp, -5.0, 10.0),
+            (|x| x.atan() - 0.5, |x| 1.0/(1.0 + x*x), -3.0, 10.0)
+        ];
+        let evals = |solver: Solver| problems.iter().map(|&(func, deriv, x0, x1)| {
+            let (root, stats) = solver.tol(1e-12).solve_with_stats(x0, x1, func, deriv).unwrap();
+            assert!(func(root).abs() < 1e-10, "{}", root);
+            stats.func_evals
+        }).sum::<usize>();
+        let (robust, miserly) = (evals(Solver::new()), evals(Solver::new().strategy(Strategy::EvalMiserly)));
+        assert!(miserly < robust, "{} vs {}", miserly, robust);
+    }
+
+    #[test]
+    fn sampled_cubic_matches_the_ends_and_the_predicted_root() {
+        let func = |x: f64| x.sin() + x.powi(3) - 0.5;
+        let deriv = |x: f64| x.cos() + 3.0*x*x;
+        let state = Solver::new().initial_state(-1.0, 2.0, &func, &deriv).unwrap();
+
+        let samples = state.sample_cubic(11);
+        assert_eq!(samples.len(), 11);
+        assert_eq!(samples[0], (-1.0, func(-1.0)));
+        assert!((samples[10].0 - 2.0).abs() < 1e-15 && (samples[10].1 - func(2.0)).abs() < 1e-12, "{:?}", samples[10]);
+        assert!(samples.windows(2).all(|w| w[0].0 < w[1].0));
+
+        // the predicted root is where the inverse cubic, from f back to x, is at f = 0
+        let inverse = state.inverse_cubic();
+        let predicted = crate::predictor::predict_root(state.x0, state.x1, state.f0, state.df0, state.f1, state.df1).unwrap();
+        assert_eq!(inverse(0.0), predicted);
+        assert_eq!((inverse(state.f0), inverse(state.f1)), (state.x0, state.x1));
+    }
+
+    #[test]
+    fn timeout_counts_the_evaluations_of_the_initial_bracket() {
+        // the two ends alone take 40ms of the 50ms allowed, so there is time for one step at most
+        let evals = Cell::new(0);
+        let slow = |x: f64| {
+            evals.set(evals.get() + 1);
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            x.sin() + x.powi(3) - 0.5
+        };
+        let result = Solver::new().tol(1e-15).timeout(std::time::Duration::from_millis(50)).solve(-1.0, 2.0, slow, |x| x.cos() + 3.0*x*x);
+        assert!(matches!(result, Err(SolveError::TimedOut { .. })), "{:?}", result);
+        assert!(evals.get() <= 3, "{} evaluations", evals.get());
+    }
+
+    #[test]
+    fn history_csv_parses_back_to_the_states() {
+        let (_, history) = Solver::new().tol(1e-12).solve_with_history(-1.0, 2.0, |x: f64| x.sin() + x.powi(3) - 0.5, |x| x.cos() + 3.0*x*x).unwrap();
+        let mut csv = Vec::new();
+        write_history_csv(&history, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("iter,x0,x1,f_min,f_max,width,log10_width"));
+        let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+        assert_eq!(rows.len(), history.len());
+        for (row, state) in rows.iter().zip(&history) {
+            let number = |i: usize| row[i].parse::<f64>().unwrap();
+            assert_eq!(row[0].parse::<usize>().unwrap(), state.n_iters);
+            assert_eq!((number(1), number(2)), (state.x0, state.x1));
+            assert_eq!((number(3), number(4)), (state.f_min(), state.f_max()));
+            assert_eq!(number(5), state.width());
+            assert_eq!(number(6), state.width().log10());
+        }
+    }
+
+    #[test]
+    fn subnormal_residuals_choose_the_right_half_of_the_bracket() {
+        // every value of func on the bracket is subnormal, and the product of any two underflows to zero
+        let func = |x: f64| (x - 0.3)*1e-308;
+        let deriv = |_| 1e-308;
+        assert!(opposite_signs(func(0.0), func(1.0)) && func(0.0)*func(1.0) == 0.0);
+
+        let cfg = Solver::new().x_tol(1e-12).f_tol(0.0);
+        let mut state = cfg.initial_state(0.0, 1.0, &func, &deriv).unwrap();
+        while state.f_min() != 0.0 && !cfg.is_converged(state.x0, state.x1, state.f0, state.f1) {
+            state = solve_step(&state, &cfg, &func, &deriv).unwrap();
+            assert!(state.x0 <= 0.3 && 0.3 <= state.x1, "[{}, {}]", state.x0, state.x1);
+        }
+        let root = cfg.solve(0.0, 1.0, func, deriv).unwrap();
+        assert!((root - 0.3).abs() < 1e-12, "{}", root);
+    }
+
+    #[test]
+    fn log_scale_finds_a_tiny_root_to_relative_precision() {
+        // as a residual tolerance, 1e-12 would be met anywhere within 1e-12 of the root
+        let root = Solver::new().tol(1e-12).solve_log_scale(1e-12, 1.0, |x| x - 3e-9, |_| 1.0).unwrap();
+        assert!((root - 3e-9).abs() < 1e-11*3e-9, "{:e}", root);
+
+        // a residual tolerance set separately still counts
+        let root = Solver::new().tol(1e-12).f_tol(1e-12).solve_log_scale(1e-12, 1.0, |x| x - 3e-9, |_| 1.0).unwrap();
+        assert!((root - 3e-9).abs() < 1e-12, "{:e}", root);
+
+        assert_eq!(Solver::new().solve_log_scale(0.0, 1.0, |x| x - 3e-9, |_| 1.0), Err(SolveError::NonBracketingInterval));
+    }
+
+    #[test]
+    fn steps_built_on_a_division_give_none_for_a_vanishing_denominator() {
+        // two of the three values are equal
+        assert_eq!(inverse_quadratic(0.0, 1.0, -1.0, 1.0, 2.0, 1.0), None);
+        assert!(inverse_quadratic(0.0, 1.0, -1.0, 1.0, 2.0, 3.0).is_some());
+
+        // both derivatives zero, or so small that the step overflows
+        assert_eq!(newton_bisection(0.0, 1.0, -1.0, 0.0, 1.0, 0.0), None);
+        assert_eq!(newton_bisection(0.0, 1.0, -1.0, 1e-310, 1.0, 0.0), None);
+        assert_eq!(newton_bisection(0.0, 1.0, -1.0, 4.0, 1.0, 0.0), Some(0.25));
+
+        // 2f'^2 - ff'' is zero for f = -1, f' = 1, f'' = -2
+        assert_eq!(halley_step(0.0, 1.0, -1.0, 1.0, 2.0, 0.5, &|_| -2.0), None);
+        assert!(halley_step(0.0, 1.0, -1.0, 1.0, 2.0, 0.5, &|_| 0.0).is_some());
+
+        // equal values in false position give the midpoint
+        assert_eq!(false_position(0.0, 1.0, 1.0, 1.0), 0.5);
+    }
+
+    #[test]
+    fn sign_flipped_derivative_is_flagged_as_suspicious() {
+        let func = |x: f64| x.sin() + x.powi(3) - 0.5;
+        let (root, stats) = Solver::new().tol(1e-12).solve_with_stats(-1.0, 2.0, func, |x| -(x.cos() + 3.0*x*x)).unwrap();
+        assert!(func(root).abs() < 1e-10);
+        assert!(stats.derivative_suspicious);
+
+        let (_, stats) = Solver::new().tol(1e-12).solve_with_stats(-1.0, 2.0, func, |x| x.cos() + 3.0*x*x).unwrap();
+        assert!(!stats.derivative_suspicious);
+    }
+
+    #[test]
+    fn suspicious_brackets_sets_how_long_the_derivatives_may_contradict_the_secant() {
+        // with the sign flipped, every bracket of e^x - 3 on [-2, 4] contradicts the secant, 17 in a row
+        let func = |x: f64| x.exp() - 3.0;
+        let flipped = |k| {
+            let solver = Solver::new().tol(1e-12).suspicious_brackets(k);
+            let mut most = 0;
+            for state in SolveIter::new(solver, -2.0, 4.0, func, |x| -x.exp()).unwrap().take(17) {
+                most = most.max(state.contradicting_derivatives());
+                assert_eq!(state.derivative_suspicious(), most > k);
+            }
+            solver.solve_with_stats(-2.0, 4.0, func, |x| -x.exp()).unwrap().1.derivative_suspicious
+        };
+        assert!(flipped(3));
+        assert!(flipped(16));
+        assert!(!flipped(17));
+
+        // while the right derivative never contradicts it at all
+        let (_, stats) = Solver::new().tol(1e-12).suspicious_brackets(0).solve_with_stats(-2.0, 4.0, func, f64::exp).unwrap();
+        assert!(!stats.derivative_suspicious);
+    }
+
+    #[test]
+    fn polished_residual_is_no_worse() {
+        let problems: [Problem; 3] = [
+            (|x| x.sin() + x.powi(3) - 0.5, |x| x.cos() + 3.0*x*x, -1.0, 2.0),
+            (|x| x.exp() - 10.0, f64::exp, -5.0, 10.0),
+            (|x| x.atan() - 0.5, |x| 1.0/(1.0 + x*x), -3.0, 10.0)
+        ];
+        let mut improved = 0;
+        for &tol in &[1e-4, 1e-8] {
+            for &(func, deriv, x0, x1) in &problems {
+                let (plain, plain_stats) = Solver::new().tol(tol).solve_with_stats(x0, x1, func, deriv).unwrap();
+                let (polished, stats) = Solver::new().tol(tol).polish(true).solve_with_stats(x0, x1, func, deriv).unwrap();
+                assert!(func(polished).abs() <= func(plain).abs(), "{} vs {}", func(polished), func(plain));
+                assert!(stats.func_evals <= plain_stats.func_evals + 1);
+                if func(polished).abs() < func(plain).abs() {
+                    improved += 1;
+                }
+            }
+        }
+        assert!(improved > 0);
+    }
+
+    #[test]
+    fn exact_roots_at_the_ends_are_returned_without_a_step() {
+        let deriv_evals = Cell::new(0);
+        let deriv = |_: f64| { deriv_evals.set(deriv_evals.get() + 1); 1.0 };
+        let solve = |x0: f64, x1: f64, func: fn(f64) -> f64| inv_cubic_solve_with_stats(x0, x1, 1e-12, 100, func, deriv);
+
+        // f0 == 0, with the other end of either sign, and the ends in either order
+        for &(x0, x1) in &[(1.0, 3.0), (3.0, 1.0), (1.0, -1.0)] {
+            let (root, stats) = solve(x0, x1, |x| x - 1.0).unwrap();
+            assert_eq!((root, stats.iterations, stats.func_evals), (1.0, 0, 2));
+        }
+        // f1 == 0, and f1 == -0.0
+        for &func in &[(|x| x - 2.0) as fn(f64) -> f64, |x| -(x - 2.0)] {
+            let (root, stats) = solve(0.0, 2.0, func).unwrap();
+            assert_eq!((root, stats.iterations, stats.func_evals), (2.0, 0, 2));
+        }
+        assert_eq!(deriv_evals.get(), 0);
+
+        // both ends roots: the lower one
+        assert_eq!(solve(2.0, -2.0, |x| x*x - 4.0).unwrap().0, -2.0);
+
+        // both nonzero and of opposite sign: a root strictly inside
+        let (root, stats) = solve(0.0, 3.0, |x| x - 1.0).unwrap();
+        assert!(root > 0.0 && root < 3.0 && (root - 1.0).abs() < 1e-12 && stats.iterations > 0);
+        assert!(deriv_evals.get() > 0);
+
+        // both nonzero and of the same sign
+        assert_eq!(solve(2.0, 3.0, |x| x - 1.0).map(|(root, _)| root), Err(SolveError::NonBracketingInterval));
+    }
+
+    #[test]
+    fn golden_iterations_and_bits_for_a_polynomial() {
+        // only +, - and * in func and deriv, so every IEEE 754 target must take exactly these steps to these bits
+        let (root, stats) = inv_cubic_solve_with_stats(0.0, 5.0, 1e-14, 100, |x: f64| x*x*x - 2.0*x - 5.0, |x| 3.0*x*x - 2.0).unwrap();
+        assert_eq!(root.to_bits(), 0x4000c1a4350819e3);
+        assert_eq!((stats.iterations, stats.func_evals, stats.deriv_evals), (11, 13, 13));
+        assert_eq!(stats.last_step, Some(StepKind::InverseCubic));
+    }
+
+    #[test]
+    fn and_needs_both_tolerances_while_or_stops_at_the_first() {
+        // flat, so the residual is within f_tol while the bracket is still wide. The root is offset from 0.3 by far
+        // less than the spacing of the floats there, so func is never exactly zero.
+        let func = |x: f64| 1e-6*(x - 0.3 + 1e-20);
+        let deriv = |_| 1e-6;
+        let solve = |combine: TolCombine| {
+            let mut last = None;
+            let solver = Solver::new().x_tol(1e-10).f_tol(1e-8).tol_combine(combine);
+            let root = solver.solve_with_callback(0.0, 1.0, func, deriv, |info| last = Some(*info)).unwrap();
+            (root, last.unwrap())
+        };
+        let (_, or) = solve(TolCombine::Or);
+        assert!(or.f_min < 1e-8 && or.dx >= 1e-10, "{:?}", or);
+
+        let (root, and) = solve(TolCombine::And);
+        assert!(and.f_min < 1e-8 && and.dx < 1e-10, "{:?}", and);
+        assert!(and.n_iters > or.n_iters);
+        assert!((root - 0.3).abs() < 1e-10);
+    }
+
+    #[test]
+    fn first_convergence_check_is_the_same_for_both_orderings() {
+        // a residual far above the tolerance, so only the width can end the solve before a step
+        let func = |x: f64| 1e3*(x - 1.0002);
+        let solver = Solver::new().x_tol(1e-3).f_tol(1e-12);
+        for &(x0, x1, narrow) in &[(1.0, 1.0009, true), (1.0, 1.0011, false)] {
+            let forward = solver.solve_with_stats(x0, x1, func, |_| 1e3).unwrap();
+            let reversed = solver.solve_with_stats(x1, x0, func, |_| 1e3).unwrap();
+            assert_eq!(forward, reversed);
+            assert_eq!(forward.1.iterations == 0, narrow, "{:?}", forward);
+
+            // and the states passed to a callback are the same, with the ends in order
+            let states = |x0, x1| {
+                let mut states = Vec::new();
+                solver.solve_with_callback(x0, x1, func, |_| 1e3, |info| states.push((info.x0, info.x1, info.dx))).unwrap();
+                states
+            };
+            assert_eq!(states(x0, x1), states(x1, x0));
+            assert!(states(x1, x0).iter().all(|&(x0, x1, dx)| x0 < x1 && dx > 0.0));
+        }
+    }
+}
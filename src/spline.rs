@@ -0,0 +1,136 @@
+/*! Piecewise cubic Hermite interpolation through any number of knots, built from two_point_cubic */
+
+use core::fmt;
+use num_traits::Float;
+
+use crate::interpolation::two_point_cubic;
+
+/** The ways in which a spline can't be built from the given knots */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplineError {
+    /// at least two knots are needed to make a segment
+    TooFewKnots,
+    /// the x values of the knots must be strictly increasing
+    UnsortedKnots
+}
+
+impl fmt::Display for SplineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SplineError::TooFewKnots => write!(f, "a spline needs at least two knots"),
+            SplineError::UnsortedKnots => write!(f, "the knots are not in strictly increasing order")
+        }
+    }
+}
+
+impl std::error::Error for SplineError {}
+
+/** What the spline does outside the range of its knots */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extrapolation {
+    /// use the value at the nearest end knot
+    Clamp,
+    /// carry on with the cubic of the first or last segment
+    Extend
+}
+
+/** A piecewise cubic that matches the value and derivative at each knot, so it is continuous along with its first
+  * derivative. By default the spline is clamped outside the range of the knots.
+  */
+#[derive(Debug, Clone, PartialEq)]
+pub struct HermiteSpline<T = f64> {
+    // (x, f(x), f'(x)) at each knot, sorted by x
+    knots: Vec<(T, T, T)>,
+    extrapolation: Extrapolation
+}
+
+impl<T: Float> HermiteSpline<T> {
+    /** A spline through the knots (x, f(x), f'(x)), which must be in strictly increasing order of x */
+    pub fn new(knots: Vec<(T, T, T)>) -> Result<Self, SplineError> {
+        if knots.len() < 2 {
+            return Err(SplineError::TooFewKnots);
+        }
+        // written so that NaN knots are rejected too
+        if !knots.windows(2).all(|w| w[0].0 < w[1].0) {
+            return Err(SplineError::UnsortedKnots);
+        }
+        Ok(HermiteSpline { knots, extrapolation: Extrapolation::Clamp })
+    }
+
+    /** What to do outside the range of the knots */
+    pub fn extrapolation(mut self, extrapolation: Extrapolation) -> Self {
+        self.extrapolation = extrapolation;
+        self
+    }
+
+    /** The knots (x, f(x), f'(x)) of the spline */
+    pub fn knots(&self) -> &[(T, T, T)] {
+        &self.knots
+    }
+
+    /** Evaluate the spline at x */
+    pub fn eval(&self, x: T) -> T {
+        let first = self.knots[0].0;
+        let last = self.knots[self.knots.len() - 1].0;
+        let x = match self.extrapolation {
+            Extrapolation::Clamp => x.max(first).min(last),
+            Extrapolation::Extend => x
+        };
+
+        // the segment [knots[i], knots[i + 1]] containing x, or the nearest end segment outside the knots
+        let i = self.knots.partition_point(|k| k.0 <= x).clamp(1, self.knots.len() - 1) - 1;
+        let (x0, f0, df0) = self.knots[i];
+        let (x1, f1, df1) = self.knots[i + 1];
+        two_point_cubic(x, x0, x1, f0, df0, f1, df1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine_knots() -> Vec<(f64, f64, f64)> {
+        [0.0, 0.4, 1.1, 1.5, 2.7, 3.0].iter().map(|&x: &f64| (x, x.sin(), x.cos())).collect()
+    }
+
+    #[test]
+    fn spline_reproduces_the_knot_values() {
+        let spline = HermiteSpline::new(sine_knots()).unwrap();
+        for &(x, f, _) in spline.knots() {
+            assert_eq!(spline.eval(x), f);
+        }
+    }
+
+    #[test]
+    fn spline_is_c1_across_the_knots() {
+        let spline = HermiteSpline::new(sine_knots()).unwrap();
+        let h = 1e-7;
+        for &(x, f, df) in &spline.knots()[1..5] {
+            let left = spline.eval(x - h);
+            let right = spline.eval(x + h);
+            assert!((left - f).abs() < 2.0*h && (right - f).abs() < 2.0*h);
+            // the one sided slopes agree with each other and with the derivative at the knot
+            assert!(((f - left)/h - df).abs() < 1e-6, "{} vs {}", (f - left)/h, df);
+            assert!(((right - f)/h - df).abs() < 1e-6, "{} vs {}", (right - f)/h, df);
+        }
+    }
+
+    #[test]
+    fn spline_extrapolates_as_configured() {
+        let knots = vec![(0.0, 1.0, 2.0), (1.0, 3.0, 2.0)];
+        let clamped = HermiteSpline::new(knots.clone()).unwrap();
+        assert_eq!((clamped.eval(-1.0), clamped.eval(5.0)), (1.0, 3.0));
+        // the single segment is the line 1 + 2x, which extends past the ends
+        let extended = clamped.extrapolation(Extrapolation::Extend);
+        assert!((extended.eval(-1.0) + 1.0).abs() < 1e-12 && (extended.eval(5.0) - 11.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn spline_needs_two_sorted_knots() {
+        assert_eq!(HermiteSpline::<f64>::new(vec![]), Err(SplineError::TooFewKnots));
+        assert_eq!(HermiteSpline::new(vec![(0.0, 1.0, 0.0)]), Err(SplineError::TooFewKnots));
+        assert_eq!(HermiteSpline::new(vec![(1.0, 1.0, 0.0), (0.0, 1.0, 0.0)]), Err(SplineError::UnsortedKnots));
+        assert_eq!(HermiteSpline::new(vec![(0.0, 1.0, 0.0), (0.0, 2.0, 0.0)]), Err(SplineError::UnsortedKnots));
+        assert_eq!(HermiteSpline::new(vec![(0.0, 1.0, 0.0), (f64::NAN, 2.0, 0.0)]), Err(SplineError::UnsortedKnots));
+    }
+}
@@ -0,0 +1,77 @@
+/*! Finding all the roots in an interval, by splitting it into a grid of brackets */
+
+use num_traits::Float;
+
+use crate::inv_cubic_solve;
+use crate::solver::opposite_signs;
+
+/** Find the roots of func in [a, b], in increasing order. The interval is split into n_subdivisions equal cells, and
+  * each cell over which func changes sign is solved with inv_cubic_solve, with at most max_iters iterations. A
+  * sample that is an exact root is returned as it is.
+  *
+  * Only sign changes are seen, so a root of even multiplicity, e.g. the double root of x^2, is missed, as are both
+  * roots of a pair that fall in the same cell. If roots may be close together, use a finer grid. A cell where the
+  * solver fails, e.g. because func isn't finite somewhere, is skipped, and a sign change through a pole such as
+  * 1/x shows up as a "root" at the pole.
+  */
+pub fn find_all_roots<T, F, D>(a: T, b: T, n_subdivisions: usize, tol: T, max_iters: usize, func: F, deriv: D) -> Vec<T>
+where
+    T: Float,
+    F: Fn(T) -> T,
+    D: Fn(T) -> T
+{
+    let n = n_subdivisions.max(1);
+    let n_t = T::from(n).unwrap();
+    let sample = |i: usize| {
+        // the last sample is exactly b, whatever the rounding
+        let x = if i == n { b } else { a + (b - a)*T::from(i).unwrap()/n_t };
+        (x, func(x))
+    };
+
+    let mut roots = Vec::new();
+    let (mut x0, mut f0) = sample(0);
+    if f0 == T::zero() {
+        roots.push(x0);
+    }
+    for i in 1..=n {
+        let (x1, f1) = sample(i);
+        if f1 == T::zero() {
+            roots.push(x1);
+        } else if opposite_signs(f0, f1) {
+            if let Ok(root) = inv_cubic_solve(x0, x1, tol, max_iters, &func, &deriv) {
+                roots.push(root);
+            }
+        }
+        x0 = x1;
+        f0 = f1;
+    }
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_roots_of_sin() {
+        let roots = find_all_roots(0.0, 10.0, 100, 1e-13, 100, |x: f64| x.sin(), |x| x.cos());
+        // 0 is a sample, and so an exact root
+        assert_eq!(roots.len(), 4);
+        assert_eq!(roots[0], 0.0);
+        for (k, root) in roots.iter().enumerate() {
+            assert!((root - k as f64*core::f64::consts::PI).abs() < 1e-13, "{} vs {}", root, k);
+        }
+    }
+
+    #[test]
+    fn double_roots_and_close_pairs_are_missed() {
+        // the double root at 1 has no sign change, and 2.95 and 3.05 fall in the same cell [2.48, 3.44]
+        let func = |x: f64| (x - 1.0).powi(2)*(x - 2.95)*(x - 3.05);
+        let deriv = |x: f64| 2.0*(x - 1.0)*(x - 2.95)*(x - 3.05) + (x - 1.0).powi(2)*(2.0*x - 6.0);
+        assert!(find_all_roots(-0.4, 4.4, 5, 1e-13, 100, func, deriv).is_empty());
+        // a finer grid splits the pair, but still misses the double root
+        let roots = find_all_roots(-0.4, 4.4, 47, 1e-13, 100, func, deriv);
+        assert_eq!(roots.len(), 2);
+        assert!((roots[0] - 2.95).abs() < 1e-12 && (roots[1] - 3.05).abs() < 1e-12, "{:?}", roots);
+    }
+}
@@ -0,0 +1,77 @@
+/*! Calling the solver from JavaScript with wasm-bindgen, where func and deriv are JavaScript functions of one number */
+
+use core::cell::RefCell;
+use js_sys::Function;
+use wasm_bindgen::prelude::*;
+
+use crate::{inv_cubic_solve, SolveError};
+
+/** Why a solve with a func and deriv that can fail didn't give a root */
+#[derive(Debug, PartialEq)]
+enum Failure<E> {
+    /// the first error returned by func or deriv, after which the solver only saw NaN
+    Thrown(E),
+    /// the solver's own error
+    Solve(SolveError)
+}
+
+/** inv_cubic_solve for a func and deriv that return an error rather than a value, e.g. a JavaScript exception. The solve
+  * carries on with NaN for the value, which stops it with a non-finite value error, but the first error is what's
+  * returned.
+  */
+fn solve_fallible<E, F, D>(x0: f64, x1: f64, tol: f64, max_iters: usize, func: F, deriv: D) -> Result<f64, Failure<E>>
+where
+    F: Fn(f64) -> Result<f64, E>,
+    D: Fn(f64) -> Result<f64, E>
+{
+    let error = RefCell::new(None);
+    let call = |f: &dyn Fn(f64) -> Result<f64, E>, x: f64| f(x).unwrap_or_else(|e| {
+        error.borrow_mut().get_or_insert(e);
+        f64::NAN
+    });
+
+    let result = inv_cubic_solve(x0, x1, tol, max_iters, |x| call(&func, x), |x| call(&deriv, x));
+    if let Some(e) = error.into_inner() {
+        return Err(Failure::Thrown(e));
+    }
+    result.map_err(Failure::Solve)
+}
+
+/** inv_cubic_solve for JavaScript, as `invCubicSolve(x0, x1, tol, maxIters, func, deriv)`. A function that throws, or
+  * returns something other than a number, stops the solve: the exception is rethrown as it was, while anything else
+  * gives a non-finite value error. Errors from the solver are thrown as an Error with the message of the SolveError.
+  */
+#[wasm_bindgen(js_name = invCubicSolve)]
+pub fn inv_cubic_solve_js(x0: f64, x1: f64, tol: f64, max_iters: usize, func: &Function, deriv: &Function) -> Result<f64, JsValue> {
+    let call = |f: &Function, x: f64| f.call1(&JsValue::NULL, &JsValue::from_f64(x)).map(|value| value.as_f64().unwrap_or(f64::NAN));
+
+    solve_fallible(x0, x1, tol, max_iters, |x| call(func, x), |x| call(deriv, x)).map_err(|e| match e {
+        Failure::Thrown(e) => e,
+        Failure::Solve(e) => js_sys::Error::new(&e.to_string()).into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the JavaScript functions can't be called natively, so these are for the adaptation to the solver's closures
+
+    #[test]
+    fn fallible_functions_solve_a_simple_equation() {
+        let root = solve_fallible::<(), _, _>(1.0, 2.0, 1e-12, 100, |x| Ok(x*x - 2.0), |x| Ok(2.0*x)).unwrap();
+        assert!((root - 2f64.sqrt()).abs() < 1e-12, "{}", root);
+    }
+
+    #[test]
+    fn the_first_error_thrown_is_returned() {
+        let func = |x: f64| if x < 1.5 { Ok(x*x - 2.0) } else { Err(x) };
+        assert_eq!(solve_fallible(1.0, 2.0, 1e-12, 100, func, |x| Ok(2.0*x)), Err(Failure::Thrown(2.0)));
+    }
+
+    #[test]
+    fn solver_errors_are_passed_on() {
+        let result = solve_fallible::<(), _, _>(2.0, 3.0, 1e-12, 100, |x| Ok(x*x - 2.0), |x| Ok(2.0*x));
+        assert_eq!(result, Err(Failure::Solve(SolveError::NonBracketingInterval)));
+    }
+}